@@ -1,12 +1,19 @@
+use crate::{notification::Urgency, wayland::MouseButton};
 use core::fmt;
 use serde::de::Visitor;
 use serde::{de, Deserialize, Deserializer};
 use smithay_client_toolkit::reexports::protocols::wlr::unstable::layer_shell::v1::client::zwlr_layer_surface_v1;
-use std::{env, fs, path::PathBuf};
+use std::{env, fs, path::PathBuf, time::Duration};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub window: WindowConfig,
+    #[serde(default)]
+    pub icons: IconConfig,
+    #[serde(default)]
+    pub bindings: BindingsConfig,
+    #[serde(default)]
+    pub keyboard: KeyboardConfig,
 }
 impl Config {
     /// Find user configuration file, or if not found does default config
@@ -19,12 +26,42 @@ impl Config {
         // Try to serialize user config file or if running
         // into problems then use the default config
         if let Ok(config) = fs::read_to_string(path) {
-            toml::from_str(&config).unwrap_or(Config::default())
+            toml::from_str(&config)
+                .map(Config::upgrade)
+                .and_then(toml::Value::try_into)
+                .unwrap_or(Config::default())
         } else {
             Config::default()
         }
     }
 
+    /// Carry settings from older config files over to where they live now,
+    /// like `window.duration` (s) that became `window.timeout` (ms)
+    fn upgrade(mut config: toml::Value) -> toml::Value {
+        let Some(window) = config.get_mut("window").and_then(toml::Value::as_table_mut) else {
+            return config;
+        };
+        let Some(duration) = window.remove("duration") else {
+            return config;
+        };
+        let Some(seconds) = duration.as_integer().filter(|seconds| *seconds >= 0) else {
+            eprintln!("Ignoring window.duration, it isn't a number of seconds");
+            return config;
+        };
+        if window.contains_key("timeout") {
+            eprintln!("Ignoring window.duration, window.timeout replaces it");
+            return config;
+        }
+
+        eprintln!("window.duration is deprecated, use window.timeout (ms) instead");
+        let millis = seconds.saturating_mul(1000).min(u32::MAX.into());
+        let mut timeout = toml::value::Table::new();
+        timeout.insert("low".into(), toml::Value::Integer(millis));
+        timeout.insert("normal".into(), toml::Value::Integer(millis));
+        window.insert("timeout".into(), toml::Value::Table(timeout));
+        config
+    }
+
     /// Builds a default `Config` instance
     fn default() -> Config {
         Config {
@@ -36,6 +73,9 @@ impl Config {
                 size: WindowSize {
                     height: 100,
                     width: 350,
+                    max_height: WindowSize::default_max_height(),
+                    max_width: WindowSize::default_max_width(),
+                    max_lines: WindowSize::default_max_lines(),
                 },
                 margin: WindowMargin {
                     top: 10,
@@ -49,8 +89,10 @@ impl Config {
                         red: 0.0,
                         green: 0.0,
                         blue: 0.0,
+                        alpha: 1.0,
                     },
                     alpha: 0.75,
+                    radius: 0.0,
                 },
                 color: WindowColor {
                     // White background
@@ -58,17 +100,28 @@ impl Config {
                         red: 1.0,
                         green: 1.0,
                         blue: 1.0,
+                        alpha: 1.0,
                     },
                     // Black text
                     fg: Rgb {
                         red: 0.0,
                         green: 0.0,
                         blue: 0.0,
+                        alpha: 1.0,
                     },
                 },
-                font_size: 15,
-                duration: 3,
+                shadow: None,
+                font: WindowConfig::default_font(),
+                font_size: 15.0,
+                timeout: WindowTimeout::default(),
+                stack: WindowStack::default(),
+                output: OutputSelection::default(),
+                layout: WindowLayout::default(),
+                animation: WindowAnimation::default(),
             },
+            icons: IconConfig::default(),
+            bindings: BindingsConfig::default(),
+            keyboard: KeyboardConfig::default(),
         }
     }
 }
@@ -86,12 +139,596 @@ pub struct WindowConfig {
     pub color: WindowColor,
     /// The border for the window
     pub border: WindowBorder,
-    /// The window's text size
+    /// The drop shadow under the window
+    /// Default = no shadow
+    #[serde(default)]
+    pub shadow: Option<WindowShadow>,
+    /// The window's font family (like "sans" or "Inter")
+    /// Default = `sans`
+    #[serde(default = "WindowConfig::default_font")]
+    pub font: String,
+    /// The window's text size (px)
     /// Default = `15`
-    pub font_size: u8,
-    /// How long the window is displayed (seconds)
-    /// Defualt = `3`
-    pub duration: u8,
+    pub font_size: f64,
+    /// How long the window is displayed per urgency level
+    #[serde(default)]
+    pub timeout: WindowTimeout,
+    /// How multiple windows are stacked
+    #[serde(default)]
+    pub stack: WindowStack,
+    /// Which output (screen) the window is displayed on
+    /// Default = `focused`
+    #[serde(default, deserialize_with = "str_to_output")]
+    pub output: OutputSelection,
+    /// Where everything goes on the window
+    #[serde(default)]
+    pub layout: WindowLayout,
+    /// How windows come, go and move around
+    #[serde(default)]
+    pub animation: WindowAnimation,
+}
+impl WindowConfig {
+    fn default_font() -> String {
+        String::from("sans")
+    }
+}
+
+/// Output Selection Options
+#[derive(Default)]
+pub enum OutputSelection {
+    /// Wherever the compositor decides, usually the focused output
+    #[default]
+    Focused,
+    /// The first output
+    Primary,
+    /// Every output, mirroring the windows on each
+    All,
+    /// The output going by a name (like "DP-1"), or with it in its description
+    Named(String),
+}
+
+/// Window Stack Configuration, for displaying multiple windows at once
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowStack {
+    /// How much space between stacked windows (px)
+    /// Default = `10`
+    pub gap: i32,
+    /// How many windows can be displayed at once, the rest wait their turn
+    /// Default = `5`
+    pub max_visible: usize,
+}
+impl Default for WindowStack {
+    fn default() -> Self {
+        WindowStack {
+            gap: 10,
+            max_visible: 5,
+        }
+    }
+}
+
+/// Window Animation Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowAnimation {
+    /// Whether windows slide in from (and out to) the edge they're anchored to
+    /// Default = `true`
+    pub slide: bool,
+    /// Whether windows fade in and out
+    /// Default = `true`
+    pub fade: bool,
+    /// Whether windows move smoothly when the stack changes
+    /// Default = `true`
+    pub reflow: bool,
+    /// How long an animation takes (ms, 0 = no animations)
+    /// Default = `200`
+    pub duration: u32,
+    /// How animations speed up and slow down
+    /// Default = `EaseOut`
+    pub easing: Easing,
+}
+impl WindowAnimation {
+    /// How long windows take to come and go
+    pub fn appear_duration(&self) -> Duration {
+        match self.slide || self.fade {
+            true => Duration::from_millis(self.duration as u64),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// How long windows take to move to their place in the stack
+    pub fn reflow_duration(&self) -> Duration {
+        match self.reflow {
+            true => Duration::from_millis(self.duration as u64),
+            false => Duration::ZERO,
+        }
+    }
+
+    /// How long swiped windows take to snap back, or carry on off screen,
+    /// once the finger lets go (they follow the finger either way)
+    pub fn swipe_duration(&self) -> Duration {
+        Duration::from_millis(self.duration as u64)
+    }
+}
+impl Default for WindowAnimation {
+    fn default() -> Self {
+        WindowAnimation {
+            slide: true,
+            fade: true,
+            reflow: true,
+            duration: 200,
+            easing: Easing::EaseOut,
+        }
+    }
+}
+
+/// Easing Curve Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+impl Easing {
+    /// How far along an animation is (`0.0` to `1.0`) at a point in its time (`0.0` to `1.0`)
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t.powi(3),
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut if t < 0.5 => 4.0 * t.powi(3),
+            Self::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+/// Window Layout Configuration, the icon sits beside a column of the app
+/// name and timestamp, the summary and the body, with the action buttons
+/// along the bottom of the window.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowLayout {
+    /// How much space between the border and the content (px)
+    /// Default = `4`
+    pub padding: i32,
+    /// How much space between the elements (px)
+    /// Default = `6`
+    pub spacing: i32,
+    /// The notification image or app icon
+    pub icon: IconElement,
+    /// The name of the app that sent the notification
+    pub app_name: TextElement,
+    /// When the notification was received
+    pub timestamp: TimestampElement,
+    /// The notification title, drawn in bold
+    pub summary: TextElement,
+    /// The notification message
+    pub body: TextElement,
+    /// The labels of the action buttons
+    pub actions: TextElement,
+    /// The progress bar of notifications with a progress `value` hint
+    pub progress: ProgressElement,
+}
+impl Default for WindowLayout {
+    fn default() -> Self {
+        WindowLayout {
+            padding: 4,
+            spacing: 6,
+            icon: IconElement::default(),
+            app_name: TextElement::default(),
+            timestamp: TimestampElement::default(),
+            summary: TextElement::default(),
+            body: TextElement::default(),
+            actions: TextElement::default(),
+            progress: ProgressElement::default(),
+        }
+    }
+}
+
+/// Icon Element Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct IconElement {
+    /// Whether the icon is displayed
+    /// Default = `true`
+    pub visible: bool,
+    /// How big of a square the icon is fit into (px)
+    /// Default = `64`
+    pub size: u32,
+    /// Which side of the text the icon is on
+    /// Default = `Left`
+    pub position: IconPosition,
+}
+impl Default for IconElement {
+    fn default() -> Self {
+        IconElement {
+            visible: true,
+            size: 64,
+            position: IconPosition::Left,
+        }
+    }
+}
+
+/// Icon Position Options
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum IconPosition {
+    Left,
+    Right,
+}
+
+/// Progress Bar Element Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct ProgressElement {
+    /// Whether the progress bar is displayed
+    /// Default = `true`
+    pub visible: bool,
+    /// How tall the progress bar is (px)
+    /// Default = `6`
+    pub height: u32,
+    /// Where the progress bar goes
+    /// Default = `Content`
+    pub position: ProgressPosition,
+    /// The color of the filled part
+    /// Default = `#3584e4`
+    #[serde(deserialize_with = "hex_to_rgb")]
+    pub color: Rgb,
+    /// The color of the empty part
+    /// Default = `#00000026`
+    #[serde(deserialize_with = "hex_to_rgb")]
+    pub background: Rgb,
+    /// How round the progress bar corners are (px)
+    /// Default = `3`
+    pub radius: f64,
+}
+impl Default for ProgressElement {
+    fn default() -> Self {
+        ProgressElement {
+            visible: true,
+            height: 6,
+            position: ProgressPosition::Content,
+            color: Rgb {
+                red: 0x35 as f64 / 255.0,
+                green: 0x84 as f64 / 255.0,
+                blue: 0xe4 as f64 / 255.0,
+                alpha: 1.0,
+            },
+            background: Rgb {
+                red: 0.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0x26 as f64 / 255.0,
+            },
+            radius: 3.0,
+        }
+    }
+}
+
+/// Progress Bar Position Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum ProgressPosition {
+    /// Under the body, as wide as the text
+    Content,
+    /// Across the bottom of the window, above the action buttons
+    Bottom,
+}
+
+/// Text Element Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TextElement {
+    /// Whether the element is displayed
+    /// Default = `true`
+    pub visible: bool,
+    /// How the element's text looks
+    #[serde(flatten)]
+    pub style: TextStyle,
+}
+impl Default for TextElement {
+    fn default() -> Self {
+        TextElement {
+            visible: true,
+            style: TextStyle::default(),
+        }
+    }
+}
+
+/// Timestamp Element Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TimestampElement {
+    /// Whether the timestamp is displayed
+    /// Default = `true`
+    pub visible: bool,
+    /// How the time is formatted (`strftime` style)
+    /// Default = `%H:%M`
+    pub format: String,
+    /// How the timestamp's text looks
+    #[serde(flatten)]
+    pub style: TextStyle,
+}
+impl Default for TimestampElement {
+    fn default() -> Self {
+        TimestampElement {
+            visible: true,
+            format: String::from("%H:%M"),
+            style: TextStyle::default(),
+        }
+    }
+}
+
+/// Text Style Configuration, passed through to Pango
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct TextStyle {
+    /// The font family (like "sans" or "Inter")
+    /// Default = the window's `font`
+    pub font: Option<String>,
+    /// The text size (px)
+    /// Default = the window's `font_size`
+    pub font_size: Option<f64>,
+    /// How thick the text is
+    /// Default = `Normal` (`Bold` for the summary)
+    pub weight: Option<FontWeight>,
+    /// Whether the text is slanted
+    /// Default = `Normal`
+    pub style: FontStyle,
+    /// How much extra space between lines (px)
+    /// Default = `0`
+    pub line_spacing: f64,
+    /// Where text that doesn't fit is cut off with an ellipsis
+    /// Default = `End`
+    pub ellipsize: Ellipsize,
+    /// Where lines are broken to wrap text
+    /// Default = `Word`
+    pub wrap: Wrap,
+    /// How the text is aligned
+    /// Default = `Left` (`Center` for action labels)
+    pub align: Option<TextAlign>,
+}
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle {
+            font: None,
+            font_size: None,
+            weight: None,
+            style: FontStyle::Normal,
+            line_spacing: 0.0,
+            ellipsize: Ellipsize::End,
+            wrap: Wrap::Word,
+            align: None,
+        }
+    }
+}
+
+/// Font Weight Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum FontWeight {
+    Thin,
+    Light,
+    Normal,
+    Medium,
+    Semibold,
+    Bold,
+    Heavy,
+}
+impl FontWeight {
+    /// Mask `FontWeight` as `Weight` for pango
+    pub fn as_weight(&self) -> pango::Weight {
+        match self {
+            Self::Thin => pango::Weight::Thin,
+            Self::Light => pango::Weight::Light,
+            Self::Normal => pango::Weight::Normal,
+            Self::Medium => pango::Weight::Medium,
+            Self::Semibold => pango::Weight::Semibold,
+            Self::Bold => pango::Weight::Bold,
+            Self::Heavy => pango::Weight::Heavy,
+        }
+    }
+}
+
+/// Font Style Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum FontStyle {
+    Normal,
+    Italic,
+    Oblique,
+}
+impl FontStyle {
+    /// Mask `FontStyle` as `Style` for pango
+    pub fn as_style(&self) -> pango::Style {
+        match self {
+            Self::Normal => pango::Style::Normal,
+            Self::Italic => pango::Style::Italic,
+            Self::Oblique => pango::Style::Oblique,
+        }
+    }
+}
+
+/// Ellipsization Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum Ellipsize {
+    /// Text that doesn't fit is clipped instead
+    None,
+    Start,
+    Middle,
+    End,
+}
+impl Ellipsize {
+    /// Mask `Ellipsize` as `EllipsizeMode` for pango
+    pub fn as_ellipsize_mode(&self) -> pango::EllipsizeMode {
+        match self {
+            Self::None => pango::EllipsizeMode::None,
+            Self::Start => pango::EllipsizeMode::Start,
+            Self::Middle => pango::EllipsizeMode::Middle,
+            Self::End => pango::EllipsizeMode::End,
+        }
+    }
+}
+
+/// Wrap Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum Wrap {
+    /// Between words
+    Word,
+    /// Between characters
+    Char,
+    /// Between words, or characters if a word doesn't fit on a line
+    WordChar,
+}
+impl Wrap {
+    /// Mask `Wrap` as `WrapMode` for pango
+    pub fn as_wrap_mode(&self) -> pango::WrapMode {
+        match self {
+            Self::Word => pango::WrapMode::Word,
+            Self::Char => pango::WrapMode::Char,
+            Self::WordChar => pango::WrapMode::WordChar,
+        }
+    }
+}
+
+/// Text Alignment Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+impl TextAlign {
+    /// Mask `TextAlign` as `Alignment` for pango
+    pub fn as_alignment(&self) -> pango::Alignment {
+        match self {
+            Self::Left => pango::Alignment::Left,
+            Self::Center => pango::Alignment::Center,
+            Self::Right => pango::Alignment::Right,
+        }
+    }
+}
+
+/// Window Timeout Configuration, used when a notification
+/// doesn't ask for an `expire_timeout` of its own.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowTimeout {
+    /// How long low urgency windows are displayed (ms)
+    /// Default = `3000`
+    pub low: u32,
+    /// How long normal urgency windows are displayed (ms)
+    /// Default = `3000`
+    pub normal: u32,
+    /// How long critical urgency windows are displayed (ms)
+    /// Default = `0` (never expires)
+    pub critical: u32,
+    /// Whether the timer is paused while the pointer is over the window
+    /// Default = `true`
+    pub pause_on_hover: bool,
+    /// How long the window is displayed at least after the pointer leaves it (ms)
+    /// Default = `1000`
+    pub resume_grace: u32,
+    /// Whether the timer starts over when a notification is replaced
+    /// Default = `true`
+    pub reset_on_replace: bool,
+}
+impl WindowTimeout {
+    /// How long to display a window for the urgency level, `None` means forever
+    pub fn for_urgency(&self, urgency: Urgency) -> Option<Duration> {
+        let millis = match urgency {
+            Urgency::Low => self.low,
+            Urgency::Normal => self.normal,
+            Urgency::Critical => self.critical,
+        };
+
+        // A timeout of 0 means never expire
+        (millis > 0).then(|| Duration::from_millis(millis as u64))
+    }
+}
+impl Default for WindowTimeout {
+    fn default() -> Self {
+        WindowTimeout {
+            low: 3000,
+            normal: 3000,
+            critical: 0,
+            pause_on_hover: true,
+            resume_grace: 1000,
+            reset_on_replace: true,
+        }
+    }
+}
+
+/// Icon Configuration
+#[derive(Deserialize)]
+pub struct IconConfig {
+    /// The XDG icon theme to look up icon names in
+    /// (falls back to `hicolor` for missing icons)
+    /// Default = `hicolor`
+    pub theme: String,
+}
+impl Default for IconConfig {
+    fn default() -> Self {
+        IconConfig {
+            theme: String::from("hicolor"),
+        }
+    }
+}
+
+/// Mouse Button Bindings Configuration, what clicking a notification does
+/// (clicking action buttons and links with the left button always uses them)
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct BindingsConfig {
+    /// Default = `DefaultAction`
+    pub left: Binding,
+    /// Default = `Dismiss`
+    pub right: Binding,
+    /// Default = `DismissAll`
+    pub middle: Binding,
+}
+impl BindingsConfig {
+    /// Get the binding of a mouse button
+    pub fn for_button(&self, button: MouseButton) -> Binding {
+        match button {
+            MouseButton::Left => self.left,
+            MouseButton::Right => self.right,
+            MouseButton::Middle => self.middle,
+        }
+    }
+}
+impl Default for BindingsConfig {
+    fn default() -> Self {
+        BindingsConfig {
+            left: Binding::DefaultAction,
+            right: Binding::Dismiss,
+            middle: Binding::DismissAll,
+        }
+    }
+}
+
+/// Mouse Button Binding Options
+#[derive(Deserialize, Clone, Copy)]
+pub enum Binding {
+    /// Do nothing
+    None,
+    /// Invoke the default action, or dismiss the notification without one
+    DefaultAction,
+    /// Dismiss the notification
+    Dismiss,
+    /// Dismiss every notification, the ones waiting to be displayed too
+    DismissAll,
+    /// Open the context menu listing all of the notification's actions
+    Menu,
+}
+
+/// Keyboard Configuration
+#[derive(Deserialize, Default)]
+pub struct KeyboardConfig {
+    /// Whether the newest notification takes keyboard focus when the
+    /// compositor gives it (usually by clicking it), to pick its actions
+    /// with the arrow keys and Enter, or dismiss it with Escape
+    /// Default = `false`
+    #[serde(default)]
+    pub interactive: bool,
 }
 
 /// Window Placement Configuration
@@ -104,6 +741,17 @@ pub struct WindowPlacement {
     /// Default = `Top`
     pub y: Placement,
 }
+impl WindowPlacement {
+    /// Whether the window is anchored to the bottom edge (otherwise the top)
+    pub fn is_bottom(&self) -> bool {
+        matches!(self.x, Placement::Bottom) || matches!(self.y, Placement::Bottom)
+    }
+
+    /// Whether the window is anchored to the left edge (otherwise the right)
+    pub fn is_left(&self) -> bool {
+        matches!(self.x, Placement::Left) || matches!(self.y, Placement::Left)
+    }
+}
 
 /// Window Placement Options
 #[derive(Deserialize)]
@@ -125,15 +773,41 @@ impl Placement {
     }
 }
 
-/// Window Size Configuration
+/// Window Size Configuration, windows grow from
+/// the minimum size to fit their content
 #[derive(Deserialize)]
 pub struct WindowSize {
-    /// How tall of a window
+    /// How tall of a window at least
     /// Default = `100`
     pub height: u32,
-    /// How wide of a window
-    /// Default = `200`
+    /// How wide of a window at least
+    /// Default = `350`
     pub width: u32,
+    /// How tall of a window at most
+    /// Default = `300`
+    #[serde(default = "WindowSize::default_max_height")]
+    pub max_height: u32,
+    /// How wide of a window at most
+    /// Default = `500`
+    #[serde(default = "WindowSize::default_max_width")]
+    pub max_width: u32,
+    /// How many lines the summary and body can each take, the rest is ellipsized
+    /// Default = `5`
+    #[serde(default = "WindowSize::default_max_lines")]
+    pub max_lines: u32,
+}
+impl WindowSize {
+    fn default_max_height() -> u32 {
+        300
+    }
+
+    fn default_max_width() -> u32 {
+        500
+    }
+
+    fn default_max_lines() -> u32 {
+        5
+    }
 }
 
 /// Window Margin Configuration
@@ -166,13 +840,62 @@ pub struct WindowBorder {
     /// Border Color Alpha (transparency / opacity)
     /// Default = `0.75`
     pub alpha: f64,
+    /// How round the window corners are (px), the border follows the corners
+    /// Default = `0`
+    #[serde(default)]
+    pub radius: f64,
 }
 
-/// Window Color Configuration
-// TODO: Border Colors?
+/// Window Shadow Configuration
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct WindowShadow {
+    /// Shadow Color (`#RRGGBBAA` for a translucent shadow)
+    /// Default = `#00000080`
+    #[serde(deserialize_with = "hex_to_rgb")]
+    pub color: Rgb,
+    /// How far right the shadow is cast (px)
+    /// Default = `0`
+    pub offset_x: i32,
+    /// How far down the shadow is cast (px)
+    /// Default = `4`
+    pub offset_y: i32,
+    /// How far the shadow fades out past the window edges (px)
+    /// Default = `12`
+    pub blur: u32,
+}
+impl WindowShadow {
+    /// How far the shadow reaches past each window edge (top, right, bottom, left)
+    pub fn extents(&self) -> (i32, i32, i32, i32) {
+        let blur = self.blur as i32;
+        (
+            (blur - self.offset_y).max(0),
+            (blur + self.offset_x).max(0),
+            (blur + self.offset_y).max(0),
+            (blur - self.offset_x).max(0),
+        )
+    }
+}
+impl Default for WindowShadow {
+    fn default() -> Self {
+        WindowShadow {
+            color: Rgb {
+                red: 0.0,
+                green: 0.0,
+                blue: 0.0,
+                alpha: 0.5,
+            },
+            offset_x: 0,
+            offset_y: 4,
+            blur: 12,
+        }
+    }
+}
+
+/// Window Color Configuration, colors can be `#RRGGBB` or `#RRGGBBAA`
 #[derive(Deserialize)]
 pub struct WindowColor {
-    /// Background color
+    /// Background color (translucent with an alpha)
     /// Default = `white`
     #[serde(deserialize_with = "hex_to_rgb")]
     pub bg: Rgb,
@@ -188,6 +911,8 @@ pub struct Rgb {
     pub red: f64,
     pub green: f64,
     pub blue: f64,
+    /// How opaque the color is, from `0.0` to `1.0`
+    pub alpha: f64,
 }
 
 /// Custom parser from hex string into rgb struct
@@ -201,23 +926,63 @@ where
         type Value = Rgb;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a hex color string")
+            formatter.write_str("a hex color string (#RRGGBB or #RRGGBBAA)")
         }
 
         fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
         where
             E: de::Error,
         {
-            // Turn hex string into integer and
-            // shift it around for the rgb values
-            let int_val = i32::from_str_radix(&value[1..], 16).map_err(de::Error::custom)?;
+            let hex = value.strip_prefix('#').unwrap_or(value);
+            if !matches!(hex.len(), 6 | 8) {
+                return Err(de::Error::invalid_value(de::Unexpected::Str(value), &self));
+            }
+
+            // Turn hex string into integer and shift it around for
+            // the rgb values, with an opaque alpha if there's none
+            let int_val = u32::from_str_radix(hex, 16).map_err(de::Error::custom)?;
+            let int_val = match hex.len() {
+                6 => int_val << 8 | 0xFF,
+                _ => int_val,
+            };
             Ok(Rgb {
-                red: ((int_val >> 16) & 0xFF) as f64 / 255.0,
-                green: ((int_val >> 8) & 0xFF) as f64 / 255.0,
-                blue: (int_val & 0xFF) as f64 / 255.0,
+                red: ((int_val >> 24) & 0xFF) as f64 / 255.0,
+                green: ((int_val >> 16) & 0xFF) as f64 / 255.0,
+                blue: ((int_val >> 8) & 0xFF) as f64 / 255.0,
+                alpha: (int_val & 0xFF) as f64 / 255.0,
             })
         }
     }
 
     deserializer.deserialize_str(RGBVisitor)
 }
+
+/// Custom parser from a string into an output selection
+fn str_to_output<'de, D>(deserializer: D) -> Result<OutputSelection, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct OutputVisitor;
+
+    impl<'de> Visitor<'de> for OutputVisitor {
+        type Value = OutputSelection;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("\"focused\", \"primary\", \"all\" or an output name")
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(match value {
+                "focused" => OutputSelection::Focused,
+                "primary" | "first" => OutputSelection::Primary,
+                "all" => OutputSelection::All,
+                name => OutputSelection::Named(name.to_owned()),
+            })
+        }
+    }
+
+    deserializer.deserialize_str(OutputVisitor)
+}
@@ -0,0 +1,127 @@
+use crate::{error::RevereError, server::ServerEvent};
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic, Interest, LoopHandle, Mode, PostAction,
+};
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::Sender,
+    time::Duration,
+};
+
+/// How long to wait on a client to send its command before giving up on it
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Commands to drive revere without focusing it (from compositor keybindings),
+/// sent over the control socket one per line, like `dismiss` or `invoke reply`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Dismiss the newest notification
+    Dismiss,
+    /// Dismiss every notification, the ones waiting to be displayed too
+    DismissAll,
+    /// Invoke one of the newest notification's actions by key,
+    /// the default action if `None`
+    Invoke(Option<String>),
+}
+impl FromStr for Command {
+    type Err = String;
+
+    /// Parse a command like `dismiss`, `dismiss all` or `invoke [key]`
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["dismiss"] => Ok(Self::Dismiss),
+            ["dismiss", "all"] => Ok(Self::DismissAll),
+            ["invoke"] => Ok(Self::Invoke(None)),
+            ["invoke", key] => Ok(Self::Invoke(Some(key.to_string()))),
+            ["open-center"] => Err(String::from("notification center not implemented")),
+            _ => Err(format!("unknown command `{}`", line.trim())),
+        }
+    }
+}
+
+/// Where the control socket lives, in the user's runtime directory
+pub fn socket_path() -> PathBuf {
+    let dir = env::var_os("XDG_RUNTIME_DIR").map_or_else(env::temp_dir, PathBuf::from);
+    dir.join("revere.sock")
+}
+
+/// Listen on the control socket, handing the commands that come in off
+/// to the main loop like the D-Bus server does with its events.
+pub fn listen(
+    event_loop: LoopHandle<'static, ()>,
+    sender: Sender<ServerEvent>,
+) -> Result<(), RevereError> {
+    // Only one revere gets the D-Bus name, so a socket left behind is stale
+    let path = socket_path();
+    if let Err(e) = fs::remove_file(&path) {
+        if e.kind() != ErrorKind::NotFound {
+            return Err(e.into());
+        }
+    }
+    let listener = UnixListener::bind(&path)?;
+    listener.set_nonblocking(true)?;
+
+    let source = Generic::new(listener, Interest::READ, Mode::Level);
+    event_loop
+        .insert_source(source, move |_, listener, _| {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        if let Err(e) = serve(stream, &sender) {
+                            eprintln!("Control socket client went away: {e}");
+                        }
+                    }
+                    // Done with the clients for this wakeup
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    // Running out of file descriptors and the like is worth
+                    // a try again next wakeup, not taking the daemon down
+                    Err(e) => {
+                        eprintln!("Failed to accept a control socket client: {e}");
+                        break;
+                    }
+                }
+            }
+            Ok(PostAction::Continue)
+        })
+        .map_err(|e| e.error)?;
+
+    Ok(())
+}
+
+/// Read a command from a client, replying `ok` if it's handed off
+/// to the main loop or with what's wrong with it if not
+fn serve(stream: UnixStream, sender: &Sender<ServerEvent>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+
+    let reply = match line.parse::<Command>() {
+        Ok(command) => match sender.send(ServerEvent::Control(command)) {
+            Ok(()) => String::from("ok"),
+            Err(_) => String::from("revere is shutting down"),
+        },
+        Err(e) => e,
+    };
+    writeln!(&stream, "{reply}")
+}
+
+/// Send a command to the running revere over the control socket
+pub fn send(command: &str) -> Result<(), RevereError> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    writeln!(stream, "{command}")?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    match reply.trim() {
+        "ok" => Ok(()),
+        reply => Err(RevereError::ControlCommandError(reply.to_owned())),
+    }
+}
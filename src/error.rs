@@ -7,10 +7,14 @@ pub enum RevereError {
     StdIoError(std::io::Error),
     DBusCnxError(dbus::Error),
     DBusMethodError(dbus::MethodErr),
+    DBusNameTaken,
+    DBusSignalError,
     WaylandCnxError(smithay_client_toolkit::reexports::client::ConnectError),
     WaylandGlobalError(smithay_client_toolkit::reexports::client::GlobalError),
     CairoSurfaceError(cairo::Error),
     CairoBorrowError(cairo::BorrowError),
+    ImageDecodeError(String),
+    ControlCommandError(String),
 }
 impl RevereError {
     pub fn message(&self) -> String {
@@ -20,6 +24,10 @@ impl RevereError {
             Self::StdIoError(_) => String::from("Error: standard output"),
             Self::DBusCnxError(_) => String::from("Error: connecting to D-Bus"),
             Self::DBusMethodError(_) => String::from("Error: issue with D-Bus method"),
+            Self::DBusNameTaken => {
+                String::from("Error: another notification server already owns the D-Bus name")
+            }
+            Self::DBusSignalError => String::from("Error: sending a D-Bus signal"),
             Self::WaylandCnxError(_) => String::from("Error: issue connecting to wayland client"),
             Self::WaylandGlobalError(_) => {
                 String::from("Error: issue with a wayland client global binding")
@@ -28,6 +36,8 @@ impl RevereError {
             Self::CairoBorrowError(e) => {
                 format!("Error: issue with cairo surface data ownership\n{:?}", e)
             }
+            Self::ImageDecodeError(e) => format!("Error: decoding an image\n{}", e),
+            Self::ControlCommandError(e) => format!("Error: revere refused the command\n{}", e),
         }
     }
 }
@@ -0,0 +1,135 @@
+use crate::notification::Urgency;
+use dbus::arg::{self, PropMap, RefArg};
+use std::collections::VecDeque;
+
+/// The standard hints of a notification, parsed from the `a{sv}`
+/// hints map of a `Notify` call (any other hints are kept as is).
+/// Not every hint is acted on yet, the ones that aren't are
+/// still modeled so things can key off them.
+#[derive(Debug, Default)]
+pub struct Hints {
+    /// `urgency`: how urgent the notification is
+    pub urgency: Urgency,
+    /// `category`: the type of notification, like "email.arrived"
+    #[allow(dead_code)]
+    pub category: Option<String>,
+    /// `desktop-entry`: the name of the sending app's desktop file (without `.desktop`)
+    pub desktop_entry: Option<String>,
+    /// `image-data`: raw image pixels to display
+    pub image_data: Option<ImageData>,
+    /// `image-path`: an icon name or a path/URI of an image to display
+    pub image_path: Option<String>,
+    /// `sound-file`: path to a sound file to play
+    #[allow(dead_code)]
+    pub sound_file: Option<String>,
+    /// `sound-name`: a themeable named sound to play
+    #[allow(dead_code)]
+    pub sound_name: Option<String>,
+    /// `suppress-sound`: don't play any sound
+    #[allow(dead_code)]
+    pub suppress_sound: bool,
+    /// `transient`: bypass any persistence
+    #[allow(dead_code)]
+    pub transient: bool,
+    /// `resident`: don't remove the notification when an action is invoked
+    pub resident: bool,
+    /// `x`: x screen position the notification should point to
+    #[allow(dead_code)]
+    pub x: Option<i32>,
+    /// `y`: y screen position the notification should point to
+    #[allow(dead_code)]
+    pub y: Option<i32>,
+    /// `action-icons`: action keys should be interpreted as icon names
+    #[allow(dead_code)]
+    pub action_icons: bool,
+    /// `value`: a progress value between 0 and 100
+    pub value: Option<i32>,
+    /// Hints revere doesn't know about
+    #[allow(dead_code)]
+    pub unknown: PropMap,
+}
+impl From<PropMap> for Hints {
+    /// Implement hints map conversion into `Hints`, taking out the known hints
+    fn from(mut hints: PropMap) -> Self {
+        Hints {
+            // The spec says a byte, but plenty of clients send other integer types
+            urgency: take_i64(&mut hints, &["urgency"])
+                .map(|level| Urgency::from(level.clamp(0, 2) as u64))
+                .unwrap_or_default(),
+            category: take_string(&mut hints, &["category"]),
+            desktop_entry: take_string(&mut hints, &["desktop-entry"]),
+            // Older spec versions used different names for the image hints
+            image_data: take(&mut hints, &["image-data", "image_data", "icon_data"])
+                .and_then(|value| ImageData::parse(&*value)),
+            image_path: take_string(&mut hints, &["image-path", "image_path"]),
+            sound_file: take_string(&mut hints, &["sound-file"]),
+            sound_name: take_string(&mut hints, &["sound-name"]),
+            suppress_sound: take_bool(&mut hints, &["suppress-sound"]),
+            transient: take_bool(&mut hints, &["transient"]),
+            resident: take_bool(&mut hints, &["resident"]),
+            x: take_i64(&mut hints, &["x"]).map(|x| x as i32),
+            y: take_i64(&mut hints, &["y"]).map(|y| y as i32),
+            action_icons: take_bool(&mut hints, &["action-icons"]),
+            value: take_i64(&mut hints, &["value"]).map(|value| value as i32),
+            unknown: hints,
+        }
+    }
+}
+
+/// Raw image pixels from the `image-data` hint, a `(iiibiiay)` struct
+#[derive(Debug)]
+pub struct ImageData {
+    pub width: i32,
+    pub height: i32,
+    /// Bytes between the start of each row
+    pub rowstride: i32,
+    pub has_alpha: bool,
+    pub bits_per_sample: i32,
+    pub channels: i32,
+    pub data: Vec<u8>,
+}
+impl ImageData {
+    /// Parse the `(iiibiiay)` struct, or `None` if it's malformed
+    fn parse(value: &(dyn RefArg + 'static)) -> Option<Self> {
+        // D-Bus structs are read in as a deque of their fields
+        let fields = arg::cast::<VecDeque<Box<dyn RefArg>>>(value)?;
+        let field_i32 = |i: usize| fields.get(i)?.as_i64().map(|field| field as i32);
+        let (width, height, rowstride) = (field_i32(0)?, field_i32(1)?, field_i32(2)?);
+        let has_alpha = field_i32(3)? != 0;
+        let (bits_per_sample, channels) = (field_i32(4)?, field_i32(5)?);
+        let data = arg::cast::<Vec<u8>>(fields.get(6)?)?.clone();
+
+        Some(ImageData {
+            width,
+            height,
+            rowstride,
+            has_alpha,
+            bits_per_sample,
+            channels,
+            data,
+        })
+    }
+}
+
+/// Take the first hint found under any of the names out of the hints map
+fn take(hints: &mut PropMap, names: &[&str]) -> Option<Box<dyn RefArg>> {
+    names
+        .iter()
+        .find_map(|name| hints.remove(*name))
+        .map(|variant| variant.0)
+}
+
+/// Take a string hint out of the hints map
+fn take_string(hints: &mut PropMap, names: &[&str]) -> Option<String> {
+    take(hints, names).and_then(|value| value.as_str().map(str::to_owned))
+}
+
+/// Take an integer hint out of the hints map
+fn take_i64(hints: &mut PropMap, names: &[&str]) -> Option<i64> {
+    take(hints, names).and_then(|value| value.as_i64())
+}
+
+/// Take a boolean hint out of the hints map (missing means `false`)
+fn take_bool(hints: &mut PropMap, names: &[&str]) -> bool {
+    take_i64(hints, names).is_some_and(|value| value != 0)
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The theme every icon theme falls back to
+const FALLBACK_THEME: &str = "hicolor";
+/// Icon file extensions revere can draw, in order of preference
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+/// Resolves icon names into icon files following the XDG Icon Theme spec
+pub struct IconTheme {
+    /// The configured theme name
+    theme: String,
+    /// Directories icon themes are looked up in, in order of priority
+    base_dirs: Vec<PathBuf>,
+    /// Parsed theme indexes by theme name (`None` if the theme doesn't exist)
+    indexes: HashMap<String, Option<ThemeIndex>>,
+    /// Previous lookups by icon name, size and scale
+    lookups: HashMap<(String, u32, u32), Option<PathBuf>>,
+}
+impl IconTheme {
+    /// Create a new `IconTheme` looking up icons in the named theme
+    pub fn new(theme: &str) -> Self {
+        // $HOME/.icons, then $XDG_DATA_DIRS/icons, then /usr/share/pixmaps
+        let mut base_dirs = Vec::new();
+        if let Ok(home_dir) = env::var("HOME") {
+            base_dirs.push(PathBuf::from(home_dir).join(".icons"));
+        }
+        base_dirs.extend(data_dirs().into_iter().map(|dir| dir.join("icons")));
+        base_dirs.push(PathBuf::from("/usr/share/pixmaps"));
+
+        IconTheme {
+            theme: theme.to_owned(),
+            base_dirs,
+            indexes: HashMap::new(),
+            lookups: HashMap::new(),
+        }
+    }
+
+    /// Find the icon file best matching the size (px) and scale for an icon name
+    pub fn lookup(&mut self, name: &str, size: u32, scale: u32) -> Option<PathBuf> {
+        let key = (name.to_owned(), size, scale);
+        if let Some(path) = self.lookups.get(&key) {
+            return path.clone();
+        }
+
+        let path = self
+            .theme_chain()
+            .into_iter()
+            .find_map(|theme| self.lookup_in_theme(&theme, name, size, scale))
+            .or_else(|| self.lookup_fallback(name));
+
+        self.lookups.insert(key, path.clone());
+        path
+    }
+
+    /// Find the icon of an application from its desktop entry (without `.desktop`)
+    pub fn desktop_entry_icon(&self, desktop_entry: &str) -> Option<String> {
+        let file_name = format!("{desktop_entry}.desktop");
+        data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications").join(&file_name))
+            .find_map(|path| fs::read_to_string(path).ok())
+            .and_then(|contents| {
+                parse_key_file(&contents)
+                    .remove("Desktop Entry")?
+                    .remove("Icon")
+                    .filter(|icon| !icon.is_empty())
+            })
+    }
+
+    /// The configured theme followed by the themes it inherits from
+    /// (depth first), always ending with the hicolor fallback theme.
+    fn theme_chain(&mut self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut pending = vec![self.theme.clone()];
+
+        while let Some(theme) = pending.pop() {
+            if theme == FALLBACK_THEME || !visited.insert(theme.clone()) {
+                continue;
+            }
+            if let Some(index) = self.index(&theme) {
+                // Push in reverse so the first parent is looked at first
+                pending.extend(index.inherits.iter().rev().cloned());
+                chain.push(theme);
+            }
+        }
+        chain.push(FALLBACK_THEME.to_owned());
+
+        chain
+    }
+
+    /// Get the parsed index of a theme, parsing it the first time
+    fn index(&mut self, theme: &str) -> Option<&ThemeIndex> {
+        if !self.indexes.contains_key(theme) {
+            let index = ThemeIndex::find(theme, &self.base_dirs);
+            self.indexes.insert(theme.to_owned(), index);
+        }
+        self.indexes.get(theme)?.as_ref()
+    }
+
+    /// Look up an icon in a single theme, an exact size match wins
+    /// otherwise the icon from the directory closest in size is used.
+    fn lookup_in_theme(
+        &mut self,
+        theme: &str,
+        name: &str,
+        size: u32,
+        scale: u32,
+    ) -> Option<PathBuf> {
+        let index = self.index(theme)?;
+
+        let mut closest: Option<(u32, PathBuf)> = None;
+        for directory in &index.directories {
+            let matches = directory.matches_size(size, scale);
+            let distance = directory.size_distance(size, scale);
+            if !matches && closest.as_ref().is_some_and(|(min, _)| distance >= *min) {
+                continue;
+            }
+
+            for root in &index.roots {
+                for extension in EXTENSIONS {
+                    let path = root
+                        .join(&directory.path)
+                        .join(format!("{name}.{extension}"));
+                    if path.is_file() {
+                        if matches {
+                            return Some(path);
+                        }
+                        closest = Some((distance, path));
+                    }
+                }
+            }
+        }
+
+        closest.map(|(_, path)| path)
+    }
+
+    /// Look for an unthemed icon directly in the base directories
+    fn lookup_fallback(&self, name: &str) -> Option<PathBuf> {
+        self.base_dirs.iter().find_map(|dir| {
+            EXTENSIONS
+                .iter()
+                .map(|extension| dir.join(format!("{name}.{extension}")))
+                .find(|path| path.is_file())
+        })
+    }
+}
+
+/// The parsed `index.theme` of an icon theme
+struct ThemeIndex {
+    /// The theme directories across all the base directories
+    roots: Vec<PathBuf>,
+    /// Themes this theme falls back to
+    inherits: Vec<String>,
+    /// The icon directories in the theme
+    directories: Vec<ThemeDirectory>,
+}
+impl ThemeIndex {
+    /// Find and parse the index of a theme in the base directories
+    fn find(theme: &str, base_dirs: &[PathBuf]) -> Option<Self> {
+        let roots: Vec<PathBuf> = base_dirs
+            .iter()
+            .map(|dir| dir.join(theme))
+            .filter(|dir| dir.is_dir())
+            .collect();
+
+        // The first index.theme found is the one that counts
+        let mut sections = roots
+            .iter()
+            .find_map(|root| fs::read_to_string(root.join("index.theme")).ok())
+            .map(|contents| parse_key_file(&contents))?;
+        let theme_section = sections.remove("Icon Theme")?;
+
+        let list = |key: &str| -> Vec<String> {
+            theme_section
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(str::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let inherits = list("Inherits");
+        let directories = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let section = sections.get(&path)?;
+                ThemeDirectory::parse(path, section)
+            })
+            .collect();
+
+        Some(ThemeIndex {
+            roots,
+            inherits,
+            directories,
+        })
+    }
+}
+
+/// How an icon theme directory matches icon sizes
+#[derive(PartialEq)]
+enum DirectoryType {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+/// An icon directory of an icon theme
+struct ThemeDirectory {
+    /// The directory path relative to the theme directory
+    path: String,
+    size: u32,
+    scale: u32,
+    kind: DirectoryType,
+    min_size: u32,
+    max_size: u32,
+    threshold: u32,
+}
+impl ThemeDirectory {
+    /// Parse a directory section of an `index.theme`
+    fn parse(path: String, section: &HashMap<String, String>) -> Option<Self> {
+        let number = |key: &str| section.get(key).and_then(|value| value.parse::<u32>().ok());
+        let size = number("Size")?;
+        let kind = match section.get("Type").map(String::as_str) {
+            Some("Fixed") => DirectoryType::Fixed,
+            Some("Scalable") => DirectoryType::Scalable,
+            _ => DirectoryType::Threshold,
+        };
+
+        Some(ThemeDirectory {
+            path,
+            size,
+            scale: number("Scale").unwrap_or(1),
+            kind,
+            min_size: number("MinSize").unwrap_or(size),
+            max_size: number("MaxSize").unwrap_or(size),
+            threshold: number("Threshold").unwrap_or(2),
+        })
+    }
+
+    /// Whether icons in the directory can be used as is for the size and scale
+    fn matches_size(&self, size: u32, scale: u32) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirectoryType::Fixed => self.size == size,
+            DirectoryType::Scalable => self.min_size <= size && size <= self.max_size,
+            DirectoryType::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size
+                    && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    /// How far off (in scaled px) icons in the directory are from the size and scale
+    fn size_distance(&self, size: u32, scale: u32) -> u32 {
+        let wanted = size * scale;
+        let (min, max) = match self.kind {
+            DirectoryType::Fixed => (self.size, self.size),
+            DirectoryType::Scalable => (self.min_size, self.max_size),
+            DirectoryType::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+
+        if wanted < min * self.scale {
+            min * self.scale - wanted
+        } else {
+            wanted.saturating_sub(max * self.scale)
+        }
+    }
+}
+
+/// The XDG data directories, `$XDG_DATA_HOME` followed by `$XDG_DATA_DIRS`
+fn data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    match env::var("XDG_DATA_HOME") {
+        Ok(data_home) if !data_home.is_empty() => dirs.push(PathBuf::from(data_home)),
+        _ => {
+            if let Ok(home_dir) = env::var("HOME") {
+                dirs.push(Path::new(&home_dir).join(".local/share"));
+            }
+        }
+    }
+
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|data_dirs| !data_dirs.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+    dirs.extend(
+        data_dirs
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from),
+    );
+
+    dirs
+}
+
+/// Parse a freedesktop key file (like `index.theme` or a `.desktop` file)
+/// into its sections of keys and values.
+fn parse_key_file(contents: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut current = None;
+
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(section) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            current = Some(section.to_owned());
+            sections.entry(section.to_owned()).or_default();
+        } else if let (Some(section), Some((key, value))) = (&current, line.split_once('=')) {
+            if let Some(entries) = sections.get_mut(section) {
+                entries
+                    .entry(key.trim().to_owned())
+                    .or_insert_with(|| value.trim().to_owned());
+            }
+        }
+    }
+
+    sections
+}
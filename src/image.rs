@@ -0,0 +1,244 @@
+use crate::{error::RevereError, hints::ImageData, icon::IconTheme, notification::Notification};
+use cairo::{Format, ImageSurface};
+use resvg::{tiny_skia, usvg};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How many decoded images to keep around before starting over
+const MAX_CACHED_IMAGES: usize = 32;
+
+/// Decoded images keyed by their path, modification time and size,
+/// so repeated notifications don't have to decode the same file again.
+pub struct ImageCache {
+    images: HashMap<(PathBuf, SystemTime, u32), ImageSurface>,
+    icons: IconTheme,
+}
+impl ImageCache {
+    /// Create a new `ImageCache` resolving icon names with the icon theme
+    pub fn new(icons: IconTheme) -> Self {
+        ImageCache {
+            images: HashMap::new(),
+            icons,
+        }
+    }
+
+    /// Load the image to display for a notification, preferring the raw
+    /// `image-data` hint over an image file (or icon name) like the spec
+    /// says to, and falling back to the icon of the app's desktop entry.
+    ///
+    /// Vector images are rasterized to fit a `size` x `size` square.
+    pub fn load_thumbnail(
+        &mut self,
+        notification: &Notification,
+        size: u32,
+    ) -> Option<ImageSurface> {
+        if let Some(image_data) = &notification.hints.image_data {
+            match image_data.to_surface() {
+                Ok(surface) => return Some(surface),
+                Err(e) => eprintln!("{e}"),
+            }
+        }
+
+        let path = notification
+            .image
+            .as_deref()
+            .and_then(|image| self.resolve(image, size))
+            .or_else(|| {
+                let desktop_entry = notification.hints.desktop_entry.as_deref()?;
+                let icon = self.icons.desktop_entry_icon(desktop_entry)?;
+                self.resolve(&icon, size)
+            })?;
+        match self.load(&path, size) {
+            Ok(surface) => Some(surface),
+            Err(e) => {
+                eprintln!("{e}");
+                None
+            }
+        }
+    }
+
+    /// Resolve an image file path or icon name into an image file
+    fn resolve(&mut self, image: &str, size: u32) -> Option<PathBuf> {
+        if image.starts_with('/') {
+            Some(PathBuf::from(image))
+        } else {
+            self.icons.lookup(image, size, 1)
+        }
+    }
+
+    /// Load an image file into a Cairo surface, decoding it only
+    /// if it isn't cached or has been modified since it was cached.
+    pub fn load(&mut self, path: &Path, size: u32) -> Result<ImageSurface, RevereError> {
+        let modified = fs::metadata(path)?.modified()?;
+        let key = (path.to_path_buf(), modified, size);
+
+        if let Some(surface) = self.images.get(&key) {
+            return Ok(surface.clone());
+        }
+
+        let surface = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("svg" | "svgz") => rasterize_svg(&fs::read(path)?, size)?,
+            _ => decode_raster(path)?,
+        };
+
+        // Keep the cache from growing forever
+        if self.images.len() >= MAX_CACHED_IMAGES {
+            self.images.clear();
+        }
+        self.images.insert(key, surface.clone());
+
+        Ok(surface)
+    }
+}
+
+/// Decode a raster image (PNG, JPEG, WebP, ICO, GIF) into a Cairo surface
+fn decode_raster(path: &Path) -> Result<ImageSurface, RevereError> {
+    let image = ::image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()
+        .map_err(|e| RevereError::ImageDecodeError(format!("{}: {e}", path.display())))?
+        .into_rgba8();
+
+    let (width, height) = image.dimensions();
+    ImageData {
+        width: width as i32,
+        height: height as i32,
+        rowstride: width as i32 * 4,
+        has_alpha: true,
+        bits_per_sample: 8,
+        channels: 4,
+        data: image.into_raw(),
+    }
+    .to_surface()
+}
+
+/// Rasterize an SVG image into a Cairo surface fitting a `size` x `size` square
+fn rasterize_svg(data: &[u8], size: u32) -> Result<ImageSurface, RevereError> {
+    let tree = usvg::Tree::from_data(data, &usvg::Options::default())
+        .map_err(|e| RevereError::ImageDecodeError(e.to_string()))?;
+
+    // Scale the image to fit while keeping its aspect ratio
+    let tree_size = tree.size();
+    let scale = size as f32 / tree_size.width().max(tree_size.height());
+    let width = (tree_size.width() * scale).ceil().max(1.0) as u32;
+    let height = (tree_size.height() * scale).ceil().max(1.0) as u32;
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or_else(|| {
+        RevereError::ImageDecodeError(format!("invalid svg size {width}x{height}"))
+    })?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    // Tiny skia already premultiplies the color channels
+    argb32_surface(
+        width as i32,
+        height as i32,
+        width as usize * 4,
+        4,
+        pixmap.data(),
+        |pixel| (pixel[3], [pixel[0], pixel[1], pixel[2]]),
+    )
+}
+
+impl ImageData {
+    /// Convert the raw pixel rows into a Cairo ARGB32 surface.
+    ///
+    /// Cairo wants each pixel as a native endian `u32` with the
+    /// color channels premultiplied by the alpha channel.
+    pub fn to_surface(&self) -> Result<ImageSurface, RevereError> {
+        if self.bits_per_sample != 8 {
+            return Err(RevereError::ImageDecodeError(format!(
+                "unsupported bits per sample {}",
+                self.bits_per_sample
+            )));
+        }
+        let expected_channels = if self.has_alpha { 4 } else { 3 };
+        if self.channels != expected_channels {
+            return Err(RevereError::ImageDecodeError(format!(
+                "{} channels doesn't match has_alpha {}",
+                self.channels, self.has_alpha
+            )));
+        }
+        if self.rowstride < 0 {
+            return Err(RevereError::ImageDecodeError(format!(
+                "invalid rowstride {}",
+                self.rowstride
+            )));
+        }
+
+        let has_alpha = self.has_alpha;
+        argb32_surface(
+            self.width,
+            self.height,
+            self.rowstride as usize,
+            self.channels as usize,
+            &self.data,
+            |pixel| {
+                let alpha = if has_alpha { pixel[3] } else { u8::MAX };
+                let premultiply = |channel: u8| ((channel as u32 * alpha as u32 + 127) / 255) as u8;
+                (
+                    alpha,
+                    [
+                        premultiply(pixel[0]),
+                        premultiply(pixel[1]),
+                        premultiply(pixel[2]),
+                    ],
+                )
+            },
+        )
+    }
+}
+
+/// Build a Cairo ARGB32 surface out of pixel rows, where `to_argb` maps each
+/// source pixel into its alpha and premultiplied red, green and blue channels.
+fn argb32_surface(
+    width: i32,
+    height: i32,
+    rowstride: usize,
+    channels: usize,
+    data: &[u8],
+    to_argb: impl Fn(&[u8]) -> (u8, [u8; 3]),
+) -> Result<ImageSurface, RevereError> {
+    if width <= 0 || height <= 0 {
+        return Err(RevereError::ImageDecodeError(format!(
+            "invalid size {width}x{height}"
+        )));
+    }
+
+    // Validate the data actually holds every row before indexing into it
+    let row_len = width as usize * channels;
+    if rowstride < row_len || data.len() < rowstride * (height as usize - 1) + row_len {
+        return Err(RevereError::ImageDecodeError(String::from(
+            "pixel data is shorter than the image size",
+        )));
+    }
+
+    let stride = Format::ARgb32.stride_for_width(width as u32)? as usize;
+    let mut pixels = vec![0; stride * height as usize];
+
+    for y in 0..height as usize {
+        let src_row = &data[y * rowstride..y * rowstride + row_len];
+        let dst_row = &mut pixels[y * stride..y * stride + width as usize * 4];
+
+        for (src, dst) in src_row
+            .chunks_exact(channels)
+            .zip(dst_row.chunks_exact_mut(4))
+        {
+            let (alpha, [red, green, blue]) = to_argb(src);
+            let pixel = u32::from_be_bytes([alpha, red, green, blue]);
+            dst.copy_from_slice(&pixel.to_ne_bytes());
+        }
+    }
+
+    let surface =
+        ImageSurface::create_for_data(pixels, Format::ARgb32, width, height, stride as i32)?;
+
+    Ok(surface)
+}
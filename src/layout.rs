@@ -0,0 +1,337 @@
+use crate::{
+    config::{IconPosition, ProgressPosition, TextStyle, WindowConfig},
+    markup::{self, Link, Markup},
+    notification::Notification,
+};
+use cairo::Context;
+use pango::{glib::DateTime, FontDescription, Layout};
+use pangocairo::functions as pango_cairo;
+use std::time::UNIX_EPOCH;
+
+/// A rectangle in surface local coordinates (px)
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// A piece of text placed on the card
+pub struct PlacedText {
+    pub layout: Layout,
+    /// Where the text goes (surface local coordinates)
+    pub origin: (f64, f64),
+    /// How opaque the text is drawn, secondary text is faded
+    pub alpha: f64,
+    /// The hyperlinks in the text
+    pub links: Vec<Link>,
+}
+impl PlacedText {
+    /// The hyperlink at a point (surface local coordinates), if there's one
+    pub fn link_at(&self, x: f64, y: f64) -> Option<&str> {
+        if self.links.is_empty() {
+            return None;
+        }
+        let (origin_x, origin_y) = self.origin;
+        let (inside, index, _) = self.layout.xy_to_index(
+            ((x - origin_x) * pango::SCALE as f64) as i32,
+            ((y - origin_y) * pango::SCALE as f64) as i32,
+        );
+        if !inside {
+            return None;
+        }
+
+        self.links
+            .iter()
+            .find(|link| link.range.contains(&(index as usize)))
+            .map(|link| link.href.as_str())
+    }
+}
+
+/// Where everything on a notification card goes: the icon beside a column
+/// of the header (app name and timestamp), summary, body and progress bar,
+/// with room left for the action buttons along the bottom.
+pub struct CardLayout {
+    /// Where the icon is fit into, if there's one
+    pub icon: Option<Rect>,
+    pub texts: Vec<PlacedText>,
+    /// Where the progress bar goes and how full it is (`0.0` to `1.0`),
+    /// if the notification has a progress value
+    pub progress: Option<(Rect, f64)>,
+    /// How wide the card would be with none of the text wrapped (px)
+    pub natural_width: f64,
+    /// How tall the card needs to be to fit everything (px)
+    pub height: f64,
+}
+impl CardLayout {
+    /// Lay a notification out on a card of the size (width, height), or with
+    /// nothing wrapped if `None` (to find out how wide it wants to be).
+    /// The height only places what goes along the bottom of the card.
+    pub fn new(
+        cr: &Context,
+        notification: &Notification,
+        has_icon: bool,
+        config: &WindowConfig,
+        size: Option<(f64, f64)>,
+    ) -> Self {
+        let width = size.map(|(width, _)| width);
+        let layout_config = &config.layout;
+        let padding = padding(config);
+        let spacing = layout_config.spacing as f64;
+
+        // The icon takes a column on one side, the text the rest
+        let icon_size =
+            (has_icon && layout_config.icon.visible).then_some(layout_config.icon.size as f64);
+        let icon_space = icon_size.map_or(0.0, |size| size + spacing);
+        let content_x = match layout_config.icon.position {
+            IconPosition::Left => padding + icon_space,
+            IconPosition::Right => padding,
+        };
+        let content_width = width.map(|width| (width - padding * 2.0 - icon_space).max(0.0));
+        let icon = icon_size.map(|size| Rect {
+            x: match layout_config.icon.position {
+                IconPosition::Left => padding,
+                IconPosition::Right => width.unwrap_or_default() - padding - size,
+            },
+            y: padding,
+            width: size,
+            height: size,
+        });
+
+        let mut texts = Vec::new();
+        let mut natural_width: f64 = 0.0;
+        let mut y = padding;
+
+        // The header, the app name with the timestamp at the end of the row
+        let timestamp = layout_config
+            .timestamp
+            .visible
+            .then(|| format_timestamp(notification, &layout_config.timestamp.format))
+            .flatten()
+            .map(|time| {
+                let style = &layout_config.timestamp.style;
+                let layout = create_pango_layout(cr, &time, style, config, 0);
+                set_width(&layout, None);
+                layout
+            });
+        let app_name =
+            (layout_config.app_name.visible && !notification.app_name.is_empty()).then(|| {
+                let text = markup::escape(&notification.app_name);
+                let (layout, _) = text_layout(cr, &layout_config.app_name.style, config, &text);
+                layout.set_height(-1);
+                layout
+            });
+        if app_name.is_some() || timestamp.is_some() {
+            let timestamp_width = timestamp
+                .as_ref()
+                .map_or(0.0, |layout| layout.pixel_size().0 as f64 + spacing);
+            let mut row_width = timestamp_width;
+            let mut row_height: f64 = 0.0;
+            if let Some(layout) = app_name {
+                set_width(&layout, content_width.map(|w| w - timestamp_width));
+                let (text_width, text_height) = layout.pixel_size();
+                row_width += text_width as f64;
+                row_height = row_height.max(text_height as f64);
+                texts.push(PlacedText {
+                    layout,
+                    origin: (content_x, y),
+                    alpha: 0.7,
+                    links: Vec::new(),
+                });
+            }
+            if let Some(layout) = timestamp {
+                let (text_width, text_height) = layout.pixel_size();
+                let x = content_x + content_width.unwrap_or_default() - text_width as f64;
+                row_height = row_height.max(text_height as f64);
+                texts.push(PlacedText {
+                    layout,
+                    origin: (x.max(content_x), y),
+                    alpha: 0.7,
+                    links: Vec::new(),
+                });
+            }
+            natural_width = natural_width.max(row_width);
+            y += row_height + spacing;
+        }
+
+        // The summary in bold, then the body under it (the only text with markup)
+        let summary =
+            (layout_config.summary.visible && !notification.summary.is_empty()).then(|| {
+                let text = markup::escape(&notification.summary);
+                let style = &layout_config.summary.style;
+                let (layout, _) = text_layout(cr, style, config, &text);
+                if let (None, Some(mut font)) = (style.weight, layout.font_description()) {
+                    font.set_weight(pango::Weight::Bold);
+                    layout.set_font_description(Some(&font));
+                }
+                (layout, Vec::new())
+            });
+        let body = (layout_config.body.visible && !notification.body.is_empty()).then(|| {
+            let body = Markup::from_body(&notification.body);
+            let (layout, valid) = text_layout(cr, &layout_config.body.style, config, &body.text);
+            (layout, if valid { body.links } else { Vec::new() })
+        });
+        for (layout, links) in summary.into_iter().chain(body) {
+            layout.set_height(-(config.size.max_lines.max(1) as i32));
+            set_width(&layout, None);
+            natural_width = natural_width.max(layout.pixel_size().0 as f64);
+            set_width(&layout, content_width);
+            let (_, text_height) = layout.pixel_size();
+            texts.push(PlacedText {
+                layout,
+                origin: (content_x, y),
+                alpha: 1.0,
+                links,
+            });
+            y += text_height as f64 + spacing;
+        }
+
+        // The progress bar goes under the body, or across the bottom of the card
+        let progress_config = &layout_config.progress;
+        let progress_value = notification
+            .hints
+            .value
+            .filter(|_| progress_config.visible)
+            .map(|value| value.clamp(0, 100) as f64 / 100.0);
+        let progress_height = progress_config.height as f64;
+        let mut progress = None;
+        if let (Some(value), ProgressPosition::Content) = (progress_value, progress_config.position)
+        {
+            let rect = Rect {
+                x: content_x,
+                y,
+                width: content_width.unwrap_or_default(),
+                height: progress_height,
+            };
+            progress = Some((rect, value));
+            y += progress_height + spacing;
+        }
+
+        // The icon sits beside the text, so whichever is taller counts
+        let content_height = (y - padding - spacing).max(0.0);
+        let mut height = padding + content_height.max(icon_size.unwrap_or_default()) + padding;
+        let mut bottom = size.map_or(0.0, |(_, height)| height) - padding;
+        if layout_config.actions.visible && notification.button_actions().next().is_some() {
+            height += button_height(config) + spacing;
+            bottom -= button_height(config) + spacing;
+        }
+        if let (Some(value), ProgressPosition::Bottom) = (progress_value, progress_config.position)
+        {
+            let rect = Rect {
+                x: padding,
+                y: bottom - progress_height,
+                width: (width.unwrap_or_default() - padding * 2.0).max(0.0),
+                height: progress_height,
+            };
+            progress = Some((rect, value));
+            height += progress_height + spacing;
+        }
+
+        CardLayout {
+            icon,
+            texts,
+            progress,
+            natural_width: padding * 2.0 + icon_space + natural_width,
+            height,
+        }
+    }
+
+    /// The hyperlink at a point (surface local coordinates), if there's one
+    pub fn link_at(&self, x: f64, y: f64) -> Option<&str> {
+        self.texts.iter().find_map(|text| text.link_at(x, y))
+    }
+}
+
+/// The space between the window edges and its content (px)
+pub fn padding(config: &WindowConfig) -> f64 {
+    (config.border.width + config.layout.padding) as f64
+}
+
+/// How tall the action buttons are (px)
+pub fn button_height(config: &WindowConfig) -> f64 {
+    font_size(&config.layout.actions.style, config) * 2.0
+}
+
+/// The text size of a text style, falling back to the window's (px)
+pub fn font_size(style: &TextStyle, config: &WindowConfig) -> f64 {
+    style.font_size.unwrap_or(config.font_size)
+}
+
+/// Helper function to create a Pango layout for text in a text style, with
+/// the font, wrapping, ellipsization and alignment set up from the config.
+/// Anything the style leaves unset falls back to the window's font.
+pub fn create_pango_layout(
+    cr: &Context,
+    text: &str,
+    style: &TextStyle,
+    config: &WindowConfig,
+    max_width: u32,
+) -> Layout {
+    // Font stuff
+    let mut font = FontDescription::new();
+    font.set_family(style.font.as_deref().unwrap_or(&config.font));
+    font.set_absolute_size(font_size(style, config) * pango::SCALE as f64);
+    font.set_style(style.style.as_style());
+    if let Some(weight) = style.weight {
+        font.set_weight(weight.as_weight());
+    }
+
+    // Layout stuff
+    let layout = pango_cairo::create_layout(cr).expect("Cannot create pango layout");
+    layout.set_font_description(Some(&font));
+    layout.set_width(max_width as i32 * pango::SCALE);
+    layout.set_wrap(style.wrap.as_wrap_mode());
+    layout.set_ellipsize(style.ellipsize.as_ellipsize_mode());
+    layout.set_spacing((style.line_spacing * pango::SCALE as f64) as i32);
+    if let Some(align) = style.align {
+        layout.set_alignment(align.as_alignment());
+    }
+    layout.set_text(text);
+
+    layout
+}
+
+/// Create the layout of a text element from Pango markup, and whether
+/// the markup was valid. Invalid markup shouldn't happen, but it's
+/// shown as is rather than nothing.
+fn text_layout(
+    cr: &Context,
+    style: &TextStyle,
+    config: &WindowConfig,
+    text: &str,
+) -> (Layout, bool) {
+    let layout = create_pango_layout(cr, "", style, config, 0);
+    let valid = match pango::parse_markup(text, '\0') {
+        Ok(_) => {
+            layout.set_markup(text);
+            true
+        }
+        Err(e) => {
+            eprintln!("Invalid notification markup: {e}");
+            layout.set_text(text);
+            false
+        }
+    };
+
+    (layout, valid)
+}
+
+/// Wrap a layout to `width` px, or not at all if `None`
+fn set_width(layout: &Layout, width: Option<f64>) {
+    match width {
+        Some(width) => layout.set_width((width.max(0.0) * pango::SCALE as f64) as i32),
+        None => layout.set_width(-1),
+    }
+}
+
+/// Format when a notification was received in local time
+fn format_timestamp(notification: &Notification, format: &str) -> Option<String> {
+    let seconds = notification
+        .received_at
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    let time = DateTime::from_unix_local(seconds as i64).ok()?;
+    time.format(format).ok().map(|time| time.to_string())
+}
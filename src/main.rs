@@ -1,93 +1,135 @@
 mod config;
+mod control;
 mod error;
+mod hints;
+mod icon;
+mod image;
+mod layout;
+mod manager;
+mod markup;
 mod notification;
+mod server;
+mod wayland;
 mod window;
 
 use config::Config;
 use dbus::{blocking::Connection, message::MatchRule};
 use error::RevereError;
+use manager::NotificationManager;
 use notification::Notification;
+use server::{NotificationServer, ServerEvent};
+use smithay_client_toolkit::reexports::calloop::{
+    generic::Generic, EventLoop, Interest, Mode, PostAction,
+};
 use std::{
-    fs::File,
+    env,
     hash::{DefaultHasher, Hash, Hasher},
-    sync::Arc,
-    time::{Duration, Instant},
+    sync::mpsc,
+    time::Duration,
 };
-use window::NotificationWindow;
+use wayland::WaylandContext;
 
-// Hacked up prototype to just set up a connection
-// with D-Bus, listen for all messages, and parse
-// them into a `Notification` to display with a
-// notification window for a few seconds.
+// Prototype notification server, it owns the
+// `org.freedesktop.Notifications` name on the D-Bus
+// session bus, turns `Notify` calls (and MPRIS track
+// changes) into a `Notification` and displays them
+// in a stack of notification windows until they expire.
+//
+// Run with arguments (like `revere dismiss`) it sends them
+// as a command to the running revere instead, so compositor
+// keybindings can drive it without focusing it.
 //
 // TODO:
-//     * Fix the issue of youtube notifications showing 
+//     * Fix the issue of youtube notifications showing
 //       without thumbnail first time.
 //     * figure out a default UI that looks nice
 //     * guess I can support XOrg as well
 
 pub fn main() -> Result<(), RevereError> {
+    // Anything on the command line is a command for the running revere
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        return control::send(&args.join(" "));
+    }
+
     // Find user config file or use default config
     let config = Config::find();
 
     // Connect to the DBus session bus
     let bus_cnx = Connection::new_session()?;
 
-    // Wrap the D-Bus connection in Arc for thread safety
-    let bus_cnx = Arc::new(bus_cnx);
+    // Channel for handing notifications from D-Bus callbacks (and commands
+    // from the control socket) to the main loop
+    let (sender, receiver) = mpsc::channel();
+
+    // Serve the desktop notifications interface
+    NotificationServer::register(&bus_cnx, sender.clone())?;
+    let control_sender = sender.clone();
 
     // The hash of the last notification which is
     // used for filtering out duplicate D-Bus messages
     let mut last_notification_hash: u64 = 0;
 
-    // Build a rule listening for all messages on the D-Bus
-    // TODO: read more of D-Bus docs for how to actually query this.
-    let match_rule = MatchRule::new();
-    bus_cnx.add_match(match_rule.clone(), move |_: (), _cnx, msg| {
-        println!("Received a message: {:?}", msg);
-        let notification = Notification::from(msg);
-        println!("Parsed into notification: {notification:?}");
+    // Media player notifications share one id, so a track
+    // change replaces the previous track's notification
+    let media_notification_id = Notification::next_id();
+
+    // Listen for media players announcing a track change
+    let match_rule = MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+        .with_path("/org/mpris/MediaPlayer2");
+    bus_cnx.add_match(match_rule, move |_: (), _cnx, msg| {
+        let mut notification = Notification::from(msg);
+        notification.id = media_notification_id;
 
         // Hash the notification
         let mut hasher_state = DefaultHasher::new();
         notification.hash(&mut hasher_state);
         let notification_hash = hasher_state.finish();
 
-        // Validate the notification is not a duplicate
-        if notification_hash != last_notification_hash {
-            // Only display notifications with a title
-            if let Some(title) = notification.title {
-                let mut thumbnail = notification
-                    .image
-                    .as_ref()
-                    .and_then(|image| File::open(image).ok());
-
-                // Create a new mutable instance of `NotificationWindow`
-                let mut notification_window = NotificationWindow::try_new(&config.window)
-                    .expect("Failed to crate notification window");
-
-                // Render the notification window for some time duration (default: 3 seconds)
-                let start_time = Instant::now();
-                while start_time.elapsed() < Duration::from_secs(config.window.duration as u64) {
-                    notification_window
-                        .event_queue
-                        .dispatch(&mut (), |_, _, _| {})
-                        .unwrap();
-                    notification_window.draw(&title, &mut thumbnail, &config.window);
-                }
-                notification_window.flush_display().ok();
-            }
+        // Only display notifications with a title which aren't a duplicate
+        if notification_hash != last_notification_hash && !notification.summary.is_empty() {
+            sender
+                .send(ServerEvent::Notify(Box::new(notification)))
+                .ok();
         }
 
-        // Update the last notification hash to the current one now that
-        // this notification is finished (notification window closed)
+        // Update the last notification hash to the current one
         last_notification_hash = notification_hash;
 
         true
     })?;
 
+    // Sleep until D-Bus or wayland have something for us
+    let mut event_loop: EventLoop<'static, ()> = EventLoop::try_new()?;
+    let bus_source = Generic::from_fd(bus_cnx.channel().watch().fd, Interest::READ, Mode::Level);
+    event_loop
+        .handle()
+        .insert_source(bus_source, |_, _, _| Ok(PostAction::Continue))
+        .map_err(|e| e.error)?;
+
+    // Take commands from the control socket, revere works fine without it
+    if let Err(e) = control::listen(event_loop.handle(), control_sender) {
+        eprintln!("Failed to listen on the control socket: {e}");
+    }
+
+    // Connect to the wayland server once for every notification window
+    let wayland = WaylandContext::connect(event_loop.handle())?;
+
+    // Keeps track of the notifications on screen
+    let mut manager = NotificationManager::new(config, wayland);
+
     // Keep it running forever eva
     loop {
-        bus_cnx.process(std::time::Duration::from_millis(1000))?;
+        // Wake up for D-Bus messages, wayland events, or when the next notification expires
+        event_loop.dispatch(manager.next_timeout(), &mut ())?;
+
+        while bus_cnx.process(Duration::ZERO)? {}
+        for event in receiver.try_iter() {
+            manager.handle(event, &bus_cnx)?;
+        }
+        manager.update(&bus_cnx)?;
+
+        // Send off any signals raised along the way
+        bus_cnx.channel().flush();
     }
 }
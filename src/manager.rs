@@ -0,0 +1,503 @@
+use crate::{
+    config::{Binding, Config},
+    control::Command,
+    error::RevereError,
+    icon::IconTheme,
+    image::ImageCache,
+    notification::{CloseReason, Notification},
+    server::{NotificationServer, ServerEvent},
+    wayland::WaylandContext,
+    window::{Click, NotificationWindow},
+};
+use cairo::ImageSurface;
+use dbus::blocking::Connection;
+use std::{
+    collections::VecDeque,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+/// The height of the "+N more" overflow indicator window (px)
+const OVERFLOW_HEIGHT: u32 = 40;
+/// How often to wake up while windows are animating, in case the
+/// compositor holds off on frame callbacks (about 60 fps)
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Keeps track of every notification on screen, stacking their
+/// windows on top of each other, and the ones waiting their turn.
+pub struct NotificationManager {
+    config: Config,
+    /// Notifications on screen, the newest one is nearest the anchored edge
+    displayed: Vec<Displayed>,
+    /// Notifications waiting for room on screen
+    queue: VecDeque<Notification>,
+    /// The "+N more" indicator shown while notifications are waiting
+    overflow: Option<Overflow>,
+    /// Windows animating away, closed once they're gone
+    closing: Vec<NotificationWindow>,
+    /// Images decoded for previous notifications
+    image_cache: ImageCache,
+    /// The wayland connection windows are created on
+    wayland: WaylandContext,
+}
+impl NotificationManager {
+    /// Create a new `NotificationManager` with nothing on screen
+    pub fn new(config: Config, wayland: WaylandContext) -> Self {
+        let image_cache = ImageCache::new(IconTheme::new(&config.icons.theme));
+        NotificationManager {
+            config,
+            displayed: Vec::new(),
+            queue: VecDeque::new(),
+            overflow: None,
+            closing: Vec::new(),
+            image_cache,
+            wayland,
+        }
+    }
+
+    /// How long until the next notification on screen expires (or the next
+    /// animation frame is due), `None` if nothing ever will
+    pub fn next_timeout(&self) -> Option<Duration> {
+        let animating = self
+            .displayed
+            .iter()
+            .flat_map(|displayed| &displayed.windows)
+            .chain(self.overflow.iter().flat_map(|overflow| &overflow.windows))
+            .chain(&self.closing)
+            .any(|window| window.is_animating());
+
+        self.displayed
+            .iter()
+            .filter_map(Displayed::remaining)
+            .chain(animating.then_some(FRAME_INTERVAL))
+            .min()
+    }
+
+    /// Handle what a client asked for over D-Bus
+    pub fn handle(&mut self, event: ServerEvent, bus_cnx: &Connection) -> Result<(), RevereError> {
+        match event {
+            ServerEvent::Notify(notification) => self.notify(*notification),
+            ServerEvent::Close(id) => self.close(id, CloseReason::Closed, bus_cnx)?,
+            ServerEvent::Control(command) => self.run(command, bus_cnx)?,
+        }
+
+        Ok(())
+    }
+
+    /// Run a command from the control socket on the newest notification
+    fn run(&mut self, command: Command, bus_cnx: &Connection) -> Result<(), RevereError> {
+        let Some(displayed) = self.displayed.first_mut() else {
+            return Ok(());
+        };
+
+        let id = displayed.notification.id;
+        match displayed.run(bus_cnx, command)? {
+            Some(done) => self.finish(id, done, bus_cnx),
+            None => Ok(()),
+        }
+    }
+
+    /// Display a notification, replacing the notification with
+    /// the same id in place whether it's on screen or waiting.
+    fn notify(&mut self, notification: Notification) {
+        let id = notification.id;
+        if let Some(displayed) = self.displayed.iter_mut().find(|d| d.notification.id == id) {
+            // Redraw the same window with the new content (restarting its timer)
+            displayed.thumbnail = self
+                .image_cache
+                .load_thumbnail(&notification, thumbnail_size(&self.config, &self.wayland));
+            let timeout_config = &self.config.window.timeout;
+            if timeout_config.reset_on_replace {
+                displayed.restart_timer(notification.timeout(timeout_config));
+            }
+            displayed.notification = notification;
+            for window in &displayed.windows {
+                window.request_redraw();
+            }
+        } else if let Some(queued) = self.queue.iter_mut().find(|queued| queued.id == id) {
+            *queued = notification;
+        } else {
+            self.queue.push_back(notification);
+        }
+    }
+
+    /// Close a notification whether it's on screen or waiting,
+    /// letting clients know why (unknown ids are ignored).
+    pub fn close(
+        &mut self,
+        id: u32,
+        reason: CloseReason,
+        bus_cnx: &Connection,
+    ) -> Result<(), RevereError> {
+        if let Some(position) = self.displayed.iter().position(|d| d.notification.id == id) {
+            for window in self.displayed.remove(position).windows {
+                self.start_closing(window);
+            }
+        } else if let Some(position) = self.queue.iter().position(|queued| queued.id == id) {
+            self.queue.remove(position);
+        } else {
+            return Ok(());
+        }
+
+        NotificationServer::notification_closed(bus_cnx, id, reason)
+    }
+
+    /// Process window input and timers, bring waiting notifications
+    /// on screen as room frees up, then redraw and restack the windows.
+    pub fn update(&mut self, bus_cnx: &Connection) -> Result<(), RevereError> {
+        // Find the notifications that are done being displayed
+        let mut finished = Vec::new();
+        for displayed in &mut self.displayed {
+            if let Some(done) = displayed.poll(bus_cnx, &self.config)? {
+                finished.push((displayed.notification.id, done));
+            }
+        }
+        for (id, done) in finished {
+            self.finish(id, done, bus_cnx)?;
+        }
+
+        // Fill any room on screen with waiting notifications
+        while self.displayed.len() < self.config.window.stack.max_visible.max(1) {
+            let Some(notification) = self.queue.pop_front() else {
+                break;
+            };
+            self.show(notification)?;
+        }
+
+        // Let the user know how many are still waiting
+        let hidden = self.queue.len();
+        match &mut self.overflow {
+            Some(_) if hidden == 0 => {
+                for window in self.overflow.take().into_iter().flat_map(|o| o.windows) {
+                    self.start_closing(window);
+                }
+            }
+            Some(overflow) if overflow.hidden != hidden => {
+                overflow.hidden = hidden;
+                for window in &overflow.windows {
+                    window.request_redraw();
+                }
+            }
+            None if hidden > 0 => {
+                let windows =
+                    self.open_windows((self.config.window.size.width, OVERFLOW_HEIGHT))?;
+                self.overflow = Some(Overflow { windows, hidden });
+            }
+            _ => {}
+        }
+
+        // Draw whatever changed (which might resize windows)
+        let window_config = &self.config.window;
+        for displayed in &mut self.displayed {
+            for window in &mut displayed.windows {
+                window.draw(
+                    &displayed.notification,
+                    displayed.thumbnail.as_ref(),
+                    window_config,
+                )?;
+            }
+        }
+        if let Some(overflow) = &mut self.overflow {
+            for window in &mut overflow.windows {
+                window.draw_overflow(overflow.hidden, window_config)?;
+            }
+        }
+
+        // Let closing windows finish going away
+        self.closing.retain_mut(|window| {
+            let done = window.is_closed() || window.is_done_closing();
+            if done {
+                window.close();
+            }
+            !done
+        });
+        for window in &mut self.closing {
+            window.draw_closing(window_config)?;
+        }
+
+        // Then restack and send it all off to the compositor
+        self.restack();
+        self.wayland.flush()
+    }
+
+    /// Close a notification that's done being displayed, or all of them
+    fn finish(&mut self, id: u32, done: Done, bus_cnx: &Connection) -> Result<(), RevereError> {
+        match done {
+            Done::Closed(reason) => self.close(id, reason, bus_cnx),
+            Done::DismissAll => {
+                let ids = self.displayed.iter().map(|d| d.notification.id);
+                let ids: Vec<_> = ids
+                    .chain(self.queue.iter().map(|queued| queued.id))
+                    .collect();
+                for id in ids {
+                    self.close(id, CloseReason::Dismissed, bus_cnx)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Animate a window away, leaving its place in the stack to the others
+    fn start_closing(&mut self, mut window: NotificationWindow) {
+        window.start_closing();
+        self.closing.push(window);
+    }
+
+    /// Put a notification on screen, on top of the stack
+    fn show(&mut self, notification: Notification) -> Result<(), RevereError> {
+        let window_config = &self.config.window;
+        let thumbnail = self
+            .image_cache
+            .load_thumbnail(&notification, thumbnail_size(&self.config, &self.wayland));
+        let size = NotificationWindow::measure(&notification, thumbnail.is_some(), window_config);
+        let windows = self.open_windows(size)?;
+        let timeout = notification.timeout(&window_config.timeout);
+
+        self.displayed.insert(
+            0,
+            Displayed {
+                notification,
+                windows,
+                thumbnail,
+                timer_started: Instant::now(),
+                timeout,
+                paused: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Open a window of the size (width, height) on each of the configured outputs
+    fn open_windows(&self, size: (u32, u32)) -> Result<Vec<NotificationWindow>, RevereError> {
+        self.wayland
+            .select_outputs(&self.config.window.output)
+            .iter()
+            .map(|output| {
+                NotificationWindow::try_new(
+                    &self.wayland,
+                    &self.config.window,
+                    output.as_ref(),
+                    size,
+                    0,
+                )
+            })
+            .collect()
+    }
+
+    /// Push each window away from the anchored edge by the
+    /// height of the windows before it, with the overflow last.
+    /// Only the newest notification can take keyboard focus.
+    fn restack(&mut self) {
+        let gap = self.config.window.stack.gap;
+        let mut offset = 0;
+        for (i, displayed) in self.displayed.iter_mut().enumerate() {
+            let interactive = self.config.keyboard.interactive && i == 0;
+            for window in &mut displayed.windows {
+                window.set_offset(offset);
+                window.set_keyboard_interactive(interactive);
+            }
+            let height = displayed.windows.iter().map(|w| w.height()).max();
+            offset += height.unwrap_or_default() as i32 + gap;
+        }
+        if let Some(overflow) = &mut self.overflow {
+            for window in &mut overflow.windows {
+                window.set_offset(offset);
+            }
+        }
+    }
+}
+
+/// The "+N more" indicator windows
+struct Overflow {
+    /// A window per output
+    windows: Vec<NotificationWindow>,
+    /// How many notifications are waiting
+    hidden: usize,
+}
+
+/// Why a notification is done being displayed
+enum Done {
+    /// It's closed for a reason
+    Closed(CloseReason),
+    /// The user dismissed every notification from it
+    DismissAll,
+}
+
+/// A notification on screen
+struct Displayed {
+    notification: Notification,
+    /// A window per output, mirroring the notification on each
+    windows: Vec<NotificationWindow>,
+    thumbnail: Option<ImageSurface>,
+    /// When the expiry timer started, the notification was displayed (or
+    /// last replaced, or the pointer left it after pausing the timer)
+    timer_started: Instant,
+    /// How long to display the notification for from when the timer
+    /// started, forever if `None`
+    timeout: Option<Duration>,
+    /// How much time was left when the timer was paused, while it's paused
+    paused: Option<Duration>,
+}
+impl Displayed {
+    /// How long until the notification expires, `None` if it never
+    /// does (or won't while its timer is paused)
+    fn remaining(&self) -> Option<Duration> {
+        if self.paused.is_some() {
+            return None;
+        }
+        let timeout = self.timeout?;
+        Some(timeout.saturating_sub(self.timer_started.elapsed()))
+    }
+
+    /// Start the expiry timer over with a new timeout
+    fn restart_timer(&mut self, timeout: Option<Duration>) {
+        self.timer_started = Instant::now();
+        self.timeout = timeout;
+        // Still paused, with the full new timeout left
+        self.paused = self.paused.and(timeout);
+    }
+
+    /// Check whether the notification is done being displayed, and why
+    fn poll(&mut self, bus_cnx: &Connection, config: &Config) -> Result<Option<Done>, RevereError> {
+        // The timer is paused while the pointer is over the notification (or
+        // it has keyboard focus, or a finger on it), leaving at least the
+        // grace period to get back to it after
+        let timeout_config = &config.window.timeout;
+        let in_use = timeout_config.pause_on_hover
+            && self.windows.iter().any(|window| {
+                window.is_hovered() || window.has_keyboard_focus() || window.is_touched()
+            });
+        match (in_use, self.paused) {
+            (true, None) => self.paused = self.remaining(),
+            (false, Some(remaining)) => {
+                let grace = Duration::from_millis(timeout_config.resume_grace as u64);
+                self.paused = None;
+                self.restart_timer(Some(remaining.max(grace)));
+            }
+            _ => {}
+        }
+        if self
+            .remaining()
+            .is_some_and(|remaining| remaining.is_zero())
+        {
+            return Ok(Some(Done::Closed(CloseReason::Expired)));
+        }
+
+        // The compositor closes windows on outputs that went away,
+        // the notification is only gone once all of its windows are
+        self.windows.retain(|window| !window.is_closed());
+        if self.windows.is_empty() {
+            return Ok(Some(Done::Closed(CloseReason::Undefined)));
+        }
+
+        // Swiping the notification sideways far enough dismisses it
+        let mut swiped = false;
+        for window in &mut self.windows {
+            swiped |= window.follow_swipe(&config.window);
+        }
+        if swiped {
+            return Ok(Some(Done::Closed(CloseReason::Dismissed)));
+        }
+
+        // Keys pressed with the notification focused are commands for it
+        if let Some(command) = self.windows.iter_mut().find_map(|w| w.take_command()) {
+            return self.run(bus_cnx, command);
+        }
+
+        // Clicking a button (or menu item) invokes its action, clicking a link
+        // opens it, and clicking anywhere else does what the mouse button is
+        // bound to (tapping it invokes the default action). Using the
+        // notification dismisses it unless it's resident
+        let Some((index, click)) = self
+            .windows
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, window)| Some((index, window.take_click()?)))
+        else {
+            return Ok(None);
+        };
+        let binding = match click {
+            Click::Action(key) => return self.invoke(bus_cnx, &key),
+            Click::Link(href) => {
+                open_link(&href);
+                return Ok(self.used());
+            }
+            Click::Body(button) => config.bindings.for_button(button),
+            Click::Tap => Binding::DefaultAction,
+        };
+        match binding {
+            Binding::None => Ok(None),
+            Binding::DefaultAction => self.run(bus_cnx, Command::Invoke(None)),
+            Binding::Dismiss => Ok(Some(Done::Closed(CloseReason::Dismissed))),
+            Binding::DismissAll => Ok(Some(Done::DismissAll)),
+            Binding::Menu => {
+                if !self.notification.actions.is_empty() {
+                    self.windows[index].open_menu();
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Run a command on the notification, returning whether it's done being
+    /// displayed and why. Invoking an action it doesn't have does nothing,
+    /// invoking the default action without one dismisses it.
+    fn run(&mut self, bus_cnx: &Connection, command: Command) -> Result<Option<Done>, RevereError> {
+        match command {
+            Command::Dismiss => Ok(Some(Done::Closed(CloseReason::Dismissed))),
+            Command::DismissAll => Ok(Some(Done::DismissAll)),
+            Command::Invoke(Some(key)) => {
+                match self.notification.actions.iter().any(|a| a.key == key) {
+                    true => self.invoke(bus_cnx, &key),
+                    false => {
+                        eprintln!(
+                            "Notification {} has no action `{key}`",
+                            self.notification.id
+                        );
+                        Ok(None)
+                    }
+                }
+            }
+            Command::Invoke(None) => match self.notification.default_action() {
+                Some(action) => {
+                    let key = action.key.clone();
+                    self.invoke(bus_cnx, &key)
+                }
+                None => Ok(Some(Done::Closed(CloseReason::Dismissed))),
+            },
+        }
+    }
+
+    /// Let the client know one of its actions was invoked
+    fn invoke(&self, bus_cnx: &Connection, key: &str) -> Result<Option<Done>, RevereError> {
+        NotificationServer::action_invoked(bus_cnx, self.notification.id, key)?;
+        Ok(self.used())
+    }
+
+    /// The notification was used, which dismisses it unless it's resident
+    fn used(&self) -> Option<Done> {
+        match self.notification.hints.resident {
+            true => None,
+            false => Some(Done::Closed(CloseReason::Dismissed)),
+        }
+    }
+}
+
+/// Thumbnails are fit into the icon area, so images are loaded (and vector
+/// images rasterized) at the icon size in buffer pixels of the highest
+/// scale output.
+fn thumbnail_size(config: &Config, wayland: &WaylandContext) -> u32 {
+    (config.window.layout.icon.size as f64 * wayland.outputs.max_scale()).ceil() as u32
+}
+
+/// Open a hyperlink with the user's preferred application
+fn open_link(href: &str) {
+    match process::Command::new("xdg-open").arg(href).spawn() {
+        // Reap the process once it's done so it doesn't linger as a zombie
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => eprintln!("Failed to open {href}: {e}"),
+    }
+}
@@ -0,0 +1,365 @@
+use std::ops::Range;
+
+/// A notification body converted from the spec's markup subset into Pango markup
+pub struct Markup {
+    /// The Pango markup to hand to `Layout::set_markup`
+    pub text: String,
+    /// The hyperlinks in the body
+    pub links: Vec<Link>,
+}
+
+/// A hyperlink in a notification body
+pub struct Link {
+    /// Where the link text is, as byte indexes into the plain text Pango lays out
+    pub range: Range<usize>,
+    pub href: String,
+}
+
+/// A formatting tag left open while converting the body
+enum OpenTag {
+    Bold,
+    Italic,
+    Underline,
+    /// A hyperlink, with the index its text starts at and where it points to
+    Link(usize, String),
+}
+impl OpenTag {
+    /// The name of the tag in the spec's markup
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Bold => "b",
+            Self::Italic => "i",
+            Self::Underline => "u",
+            Self::Link(..) => "a",
+        }
+    }
+
+    /// The Pango markup closing the tag
+    fn close(&self) -> &'static str {
+        match self {
+            Self::Bold => "</b>",
+            Self::Italic => "</i>",
+            Self::Underline => "</u>",
+            Self::Link(..) => "</span>",
+        }
+    }
+}
+
+impl Markup {
+    /// Convert a body in the spec's markup subset (`<b>`, `<i>`, `<u>`,
+    /// `<a href>` and `<img>`) into Pango markup, where unknown tags are
+    /// dropped (keeping their text) and everything else is escaped.
+    pub fn from_body(body: &str) -> Self {
+        let mut markup = Markup {
+            text: String::new(),
+            links: Vec::new(),
+        };
+        let mut open_tags: Vec<OpenTag> = Vec::new();
+        let mut plain_len = 0;
+
+        let mut rest = body;
+        while !rest.is_empty() {
+            // Anything up to the next tag is text
+            let text_end = rest.find('<').unwrap_or(rest.len());
+            if text_end > 0 {
+                let text = decode_entities(&rest[..text_end]);
+                plain_len += text.len();
+                markup.text.push_str(&escape(&text));
+                rest = &rest[text_end..];
+                continue;
+            }
+
+            // A `<` that never closes is just text
+            let Some(tag_end) = rest.find('>') else {
+                plain_len += rest.len();
+                markup.text.push_str(&escape(rest));
+                break;
+            };
+            let tag = Tag::parse(&rest[1..tag_end]);
+            rest = &rest[tag_end + 1..];
+
+            match (tag.name.as_str(), tag.closing) {
+                ("b", false) => {
+                    markup.text.push_str("<b>");
+                    open_tags.push(OpenTag::Bold);
+                }
+                ("i", false) => {
+                    markup.text.push_str("<i>");
+                    open_tags.push(OpenTag::Italic);
+                }
+                ("u", false) => {
+                    markup.text.push_str("<u>");
+                    open_tags.push(OpenTag::Underline);
+                }
+                ("a", false) => {
+                    markup.text.push_str("<span underline=\"single\">");
+                    let href = tag.attribute("href").unwrap_or_default();
+                    open_tags.push(OpenTag::Link(plain_len, href));
+                }
+                // Images aren't drawn inline, their alternative text is
+                ("img", _) => {
+                    let alt = tag.attribute("alt").unwrap_or_default();
+                    plain_len += alt.len();
+                    markup.text.push_str(&escape(&alt));
+                }
+                ("br", _) => {
+                    plain_len += 1;
+                    markup.text.push('\n');
+                }
+                (name, true) => {
+                    // Close everything opened since, Pango wants tags properly nested
+                    if let Some(position) = open_tags.iter().rposition(|open| open.name() == name) {
+                        for open in open_tags.drain(position..).rev() {
+                            markup.close(open, plain_len);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Close whatever was left open
+        while let Some(open) = open_tags.pop() {
+            markup.close(open, plain_len);
+        }
+
+        markup
+    }
+
+    /// Close an open tag which ends at `plain_len`
+    fn close(&mut self, open: OpenTag, plain_len: usize) {
+        self.text.push_str(open.close());
+        if let OpenTag::Link(start, href) = open {
+            if !href.is_empty() && start < plain_len {
+                self.links.push(Link {
+                    range: start..plain_len,
+                    href,
+                });
+            }
+        }
+    }
+}
+
+/// A tag in the spec's markup, like `<a href="https://example.com">`
+struct Tag {
+    /// The lowercase tag name
+    name: String,
+    /// Whether it's a closing tag, like `</a>`
+    closing: bool,
+    /// The tag's attributes by lowercase name, with entities decoded
+    attributes: Vec<(String, String)>,
+}
+impl Tag {
+    /// Parse the insides of a tag (what's between `<` and `>`)
+    fn parse(inside: &str) -> Self {
+        let inside = inside.trim().trim_end_matches('/');
+        let (closing, inside) = match inside.strip_prefix('/') {
+            Some(inside) => (true, inside),
+            None => (false, inside),
+        };
+        let name_end = inside
+            .find(|c: char| c.is_whitespace())
+            .unwrap_or(inside.len());
+        let name = inside[..name_end].to_ascii_lowercase();
+
+        // Attributes look like `name="value"`, `name='value'` or `name=value`
+        let mut attributes = Vec::new();
+        let mut rest = inside[name_end..].trim_start();
+        while let Some(equals) = rest.find('=') {
+            let attribute = rest[..equals].trim().to_ascii_lowercase();
+            let value_start = rest[equals + 1..].trim_start();
+            let (value, after) = match value_start.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    let value = &value_start[1..];
+                    let end = value.find(quote).unwrap_or(value.len());
+                    (&value[..end], value.get(end + 1..).unwrap_or_default())
+                }
+                _ => {
+                    let end = value_start
+                        .find(|c: char| c.is_whitespace())
+                        .unwrap_or(value_start.len());
+                    (&value_start[..end], &value_start[end..])
+                }
+            };
+            attributes.push((attribute, decode_entities(value)));
+            rest = after.trim_start();
+        }
+
+        Tag {
+            name,
+            closing,
+            attributes,
+        }
+    }
+
+    /// Get the value of an attribute
+    fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+/// Escape text so Pango doesn't mistake any of it for markup
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Decode the XML entities in text, leaving anything unknown as is
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .and_then(|end| Some((decode_entity(&rest[1..end])?, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+/// Decode a single entity (without the `&` and `;`)
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some('\u{a0}'),
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The plain text Pango lays out for a body, which link ranges index into
+    fn plain(body: &str) -> String {
+        let mut plain = String::new();
+        let mut in_tag = false;
+        for c in Markup::from_body(body).text.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => plain.push(c),
+                _ => {}
+            }
+        }
+        decode_entities(&plain)
+    }
+
+    #[test]
+    fn escapes_text() {
+        assert_eq!(Markup::from_body("a < b & c").text, "a &lt; b &amp; c");
+        assert_eq!(Markup::from_body("\"quoted\"").text, "&quot;quoted&quot;");
+    }
+
+    #[test]
+    fn keeps_formatting_tags() {
+        let markup = Markup::from_body("<b>bold</b> <i>italic</i> <U>under</U>");
+        assert_eq!(markup.text, "<b>bold</b> <i>italic</i> <u>under</u>");
+    }
+
+    #[test]
+    fn drops_unknown_tags_keeping_their_text() {
+        let markup = Markup::from_body("<font color=\"red\">hi</font><script>x</script>");
+        assert_eq!(markup.text, "hix");
+    }
+
+    #[test]
+    fn nests_tags_properly() {
+        assert_eq!(
+            Markup::from_body("<b><i>x</b>y</i>").text,
+            "<b><i>x</i></b>y"
+        );
+        assert_eq!(Markup::from_body("<b>open").text, "<b>open</b>");
+        assert_eq!(Markup::from_body("stray</b>").text, "stray");
+    }
+
+    #[test]
+    fn decodes_entities() {
+        let markup = Markup::from_body("&amp;&lt;&#65;&#x42;&bogus; &");
+        assert_eq!(markup.text, "&amp;&lt;AB&amp;bogus; &amp;");
+    }
+
+    #[test]
+    fn replaces_images_with_their_alt_text() {
+        let markup = Markup::from_body("a <img src=\"pic.png\" alt=\"&lt;pic&gt;\"/> b");
+        assert_eq!(markup.text, "a &lt;pic&gt; b");
+    }
+
+    #[test]
+    fn maps_link_ranges() {
+        let body = "see <a href=\"https://example.com\">here</a> or <a href='x'><b>there</b></a>";
+        let markup = Markup::from_body(body);
+        let plain = plain(body);
+        assert_eq!(plain, "see here or there");
+
+        let links: Vec<_> = markup
+            .links
+            .iter()
+            .map(|link| (&plain[link.range.clone()], link.href.as_str()))
+            .collect();
+        assert_eq!(links, [("here", "https://example.com"), ("there", "x")]);
+    }
+
+    #[test]
+    fn maps_link_ranges_after_non_ascii_text() {
+        let body = "héllo &amp; 日本 <a href=u>wörld</a>";
+        let markup = Markup::from_body(body);
+        let plain = plain(body);
+        assert_eq!(plain, "héllo & 日本 wörld");
+
+        let [link] = markup.links.as_slice() else {
+            panic!("expected one link, got {}", markup.links.len());
+        };
+        assert_eq!(link.range, 16..22);
+        assert_eq!(&plain[link.range.clone()], "wörld");
+    }
+
+    #[test]
+    fn ignores_links_without_href() {
+        let markup = Markup::from_body("<a>nowhere</a> <a href=\"u\"></a>");
+        assert_eq!(
+            markup.text,
+            "<span underline=\"single\">nowhere</span> <span underline=\"single\"></span>"
+        );
+        assert!(markup.links.is_empty());
+    }
+}
@@ -1,24 +1,181 @@
+use crate::{config::WindowTimeout, hints::Hints, server::NotifyArgs};
 use dbus::arg::{self, PropMap, RefArg, Variant};
-use dbus::message::MessageType;
 use dbus::Message;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// The next notification id to hand out (ids start at 1, 0 means "none")
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
 
 /// The Revere Notification type
 #[derive(Debug)]
 pub struct Notification {
-    pub kind: MessageType,
-    pub path: String,
-    pub title: Option<String>,
-    pub summary: Option<String>,
+    pub id: u32,
+    pub app_name: String,
+    // TODO: `app_icon` isn't rendered on its own, only as the fallback image
+    #[allow(dead_code)]
+    pub app_icon: Option<String>,
+    pub summary: String,
+    pub body: String,
+    /// An image file path or an icon name from the icon theme
     pub image: Option<String>,
+    pub actions: Vec<Action>,
+    pub hints: Hints,
+    pub expire_timeout: i32,
+    /// When the notification was received
+    pub received_at: SystemTime,
 }
+impl Notification {
+    /// Build a `Notification` from the arguments of a `Notify` method call
+    pub fn from_notify(args: NotifyArgs) -> Self {
+        let (app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout) = args;
+
+        // A `replaces_id` of 0 means this is a brand new notification. Other
+        // ids are kept whether they're still around or not, so they're never
+        // handed out again to an unrelated notification
+        let id = match replaces_id {
+            0 => Self::next_id(),
+            id => {
+                NEXT_ID.fetch_max(id.wrapping_add(1), Ordering::Relaxed);
+                id
+            }
+        };
+
+        let hints = Hints::from(hints);
+
+        // The `image-path` hint takes priority over the app icon, either
+        // can be a file path, a file URI or an icon name from the icon theme
+        let image = [hints.image_path.as_deref(), Some(app_icon.as_str())]
+            .into_iter()
+            .flatten()
+            .find(|image| !image.is_empty())
+            .map(|image| Self::image_path(image).unwrap_or_else(|| image.to_owned()));
+
+        Notification {
+            id,
+            app_name,
+            app_icon: Some(app_icon).filter(|icon| !icon.is_empty()),
+            summary,
+            body,
+            image,
+            actions: Action::from_pairs(actions),
+            hints,
+            expire_timeout,
+            received_at: SystemTime::now(),
+        }
+    }
+
+    /// How long to display the notification, `None` means until it's closed.
+    ///
+    /// An `expire_timeout` of -1 leaves it up to the server (the configured
+    /// timeout for the urgency level), 0 never expires, and >0 is milliseconds.
+    pub fn timeout(&self, config: &WindowTimeout) -> Option<Duration> {
+        match self.expire_timeout {
+            0 => None,
+            millis if millis > 0 => Some(Duration::from_millis(millis as u64)),
+            _ => config.for_urgency(self.hints.urgency),
+        }
+    }
+
+    /// The action invoked when the notification itself is clicked, if any
+    pub fn default_action(&self) -> Option<&Action> {
+        self.actions.iter().find(|action| action.is_default())
+    }
+
+    /// The actions displayed as buttons on the notification
+    pub fn button_actions(&self) -> impl Iterator<Item = &Action> {
+        self.actions.iter().filter(|action| !action.is_default())
+    }
+
+    /// Hand out a new unique notification id
+    pub fn next_id() -> u32 {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        // Skip 0 after wrapping around, it's reserved for "no id"
+        if id == 0 {
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        } else {
+            id
+        }
+    }
+
+    /// Parse a file path out of an icon string, if it is one
+    fn image_path(icon: &str) -> Option<String> {
+        if let Some(path) = icon.strip_prefix("file://") {
+            Some(path.to_owned())
+        } else if icon.starts_with('/') {
+            Some(icon.to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+/// A notification action the user can invoke
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    /// The key sent back to the client in `ActionInvoked`
+    pub key: String,
+    /// The label displayed to the user
+    pub label: String,
+}
+impl Action {
+    /// The key of the action invoked by clicking the notification itself
+    pub const DEFAULT_KEY: &'static str = "default";
+
+    /// Parse actions out of the flat `[key, label, key, label, ...]` list
+    /// from a `Notify` call, dropping a trailing key without a label.
+    fn from_pairs(actions: Vec<String>) -> Vec<Action> {
+        let mut actions = actions.into_iter();
+        let mut parsed = Vec::new();
+        while let (Some(key), Some(label)) = (actions.next(), actions.next()) {
+            parsed.push(Action { key, label });
+        }
+        parsed
+    }
+
+    /// Whether this is the action invoked by clicking the notification itself
+    pub fn is_default(&self) -> bool {
+        self.key == Self::DEFAULT_KEY
+    }
+}
+
+/// How urgent a notification is, from the `urgency` hint
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+impl From<u64> for Urgency {
+    fn from(level: u64) -> Self {
+        match level {
+            0 => Self::Low,
+            2 => Self::Critical,
+            _ => Self::Normal,
+        }
+    }
+}
+
+/// Why a notification was closed, as defined by the Desktop Notifications spec
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloseReason {
+    /// The notification expired
+    Expired = 1,
+    /// The notification was dismissed by the user
+    Dismissed = 2,
+    /// The notification was closed by a call to `CloseNotification`
+    Closed = 3,
+    /// Undefined/reserved reasons
+    Undefined = 4,
+}
+
 impl From<&Message> for Notification {
-    /// Implement DBus Message conversion into a Notification
+    /// Implement MPRIS `PropertiesChanged` message conversion into a Notification
     fn from(msg: &Message) -> Self {
-        let kind = msg.msg_type();
-        let path = msg.path().unwrap().to_string();
-        let (mut title, summary, mut image) = (None, None, None);
+        let (mut summary, mut image) = (String::new(), None);
 
         // Parse out a title/action from a PropertiesChanged member message
         if msg.member().expect("some interface member").to_string() == *"PropertiesChanged" {
@@ -39,34 +196,36 @@ impl From<&Message> for Notification {
                 let variant = &metadata_variant.0;
                 let map: &PropMap = arg::cast(variant).unwrap();
 
-                title = Some(
-                    map.get("xesam:title")
-                        .expect("some title")
-                        .as_str()
-                        .expect("a string")
-                        .to_owned(),
-                );
+                if let Some(title) = map.get("xesam:title").and_then(|value| value.as_str()) {
+                    summary = title.to_owned();
+                }
 
                 image = map
                     .get("mpris:artUrl")
                     .map(|value| value.as_str())
-                    .and_then(|value| value.map(|s| s.to_owned().replace("file://", "")));
+                    .and_then(|value| value.and_then(Self::image_path));
             }
         }
 
         Notification {
-            kind,
-            path,
-            title,
+            // Media player notifications get their id from the listener
+            id: 0,
+            app_name: String::new(),
+            app_icon: None,
             summary,
+            body: String::new(),
             image,
+            actions: Vec::new(),
+            hints: Hints::default(),
+            expire_timeout: -1,
+            received_at: SystemTime::now(),
         }
     }
 }
 impl Hash for Notification {
     /// Compute a hash for the `Notification`
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.title.hash(state);
+        self.summary.hash(state);
         self.image.hash(state);
     }
 }
@@ -0,0 +1,182 @@
+use crate::{
+    control::Command,
+    error::RevereError,
+    notification::{CloseReason, Notification},
+};
+use dbus::{
+    arg::PropMap,
+    blocking::{stdintf::org_freedesktop_dbus::RequestNameReply, Connection},
+    channel::{MatchingReceiver, Sender as _},
+    message::MatchRule,
+    Message,
+};
+use dbus_crossroads::{Crossroads, IfaceToken};
+use std::sync::mpsc::Sender;
+
+/// The well known bus name of a notification server
+pub const NOTIFICATIONS_NAME: &str = "org.freedesktop.Notifications";
+/// The object path of a notification server
+pub const NOTIFICATIONS_PATH: &str = "/org/freedesktop/Notifications";
+/// The version of the Desktop Notifications spec revere implements
+const SPEC_VERSION: &str = "1.2";
+
+/// The arguments of a `Notify` method call, in order:
+/// app_name, replaces_id, app_icon, summary, body, actions, hints, expire_timeout
+pub type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    PropMap,
+    i32,
+);
+
+/// Events the D-Bus server (and the control socket) hand off to the main loop
+#[derive(Debug)]
+pub enum ServerEvent {
+    /// A client asked for a notification to be displayed
+    Notify(Box<Notification>),
+    /// A client asked for a notification to be closed
+    Close(u32),
+    /// A command came in over the control socket
+    Control(Command),
+}
+
+/// The `org.freedesktop.Notifications` D-Bus server
+pub struct NotificationServer {
+    sender: Sender<ServerEvent>,
+}
+impl NotificationServer {
+    /// Claim the notifications bus name and start serving the
+    /// notifications interface on the given D-Bus connection.
+    pub fn register(bus_cnx: &Connection, sender: Sender<ServerEvent>) -> Result<(), RevereError> {
+        // Only one notification server can own the name at a time
+        let reply = bus_cnx.request_name(NOTIFICATIONS_NAME, false, true, true)?;
+        if reply != RequestNameReply::PrimaryOwner {
+            return Err(RevereError::DBusNameTaken);
+        }
+
+        // Build the interface and export it at the notifications path
+        let mut crossroads = Crossroads::new();
+        let interface = Self::register_interface(&mut crossroads);
+        crossroads.insert(NOTIFICATIONS_PATH, &[interface], Self { sender });
+
+        // Route all incoming method calls through the crossroads
+        bus_cnx.start_receive(
+            MatchRule::new_method_call(),
+            Box::new(move |msg, cnx| {
+                if crossroads.handle_message(msg, cnx).is_err() {
+                    eprintln!("Failed to handle D-Bus method call");
+                }
+                true
+            }),
+        );
+
+        Ok(())
+    }
+
+    /// Let clients know a notification was closed, and why
+    pub fn notification_closed(
+        bus_cnx: &Connection,
+        id: u32,
+        reason: CloseReason,
+    ) -> Result<(), RevereError> {
+        let signal = Message::signal(
+            &NOTIFICATIONS_PATH.into(),
+            &NOTIFICATIONS_NAME.into(),
+            &"NotificationClosed".into(),
+        )
+        .append2(id, reason as u32);
+
+        bus_cnx
+            .send(signal)
+            .map(|_| ())
+            .map_err(|_| RevereError::DBusSignalError)
+    }
+
+    /// Let clients know the user invoked one of a notification's actions
+    pub fn action_invoked(
+        bus_cnx: &Connection,
+        id: u32,
+        action_key: &str,
+    ) -> Result<(), RevereError> {
+        let signal = Message::signal(
+            &NOTIFICATIONS_PATH.into(),
+            &NOTIFICATIONS_NAME.into(),
+            &"ActionInvoked".into(),
+        )
+        .append2(id, action_key);
+
+        bus_cnx
+            .send(signal)
+            .map(|_| ())
+            .map_err(|_| RevereError::DBusSignalError)
+    }
+
+    /// Describe the `org.freedesktop.Notifications` interface methods and signals
+    fn register_interface(crossroads: &mut Crossroads) -> IfaceToken<Self> {
+        crossroads.register(NOTIFICATIONS_NAME, |builder| {
+            builder.method(
+                "Notify",
+                (
+                    "app_name",
+                    "replaces_id",
+                    "app_icon",
+                    "summary",
+                    "body",
+                    "actions",
+                    "hints",
+                    "expire_timeout",
+                ),
+                ("id",),
+                |_, server: &mut Self, args: NotifyArgs| {
+                    let notification = Notification::from_notify(args);
+                    let id = notification.id;
+                    server.send(ServerEvent::Notify(Box::new(notification)))?;
+                    Ok((id,))
+                },
+            );
+            builder.method(
+                "CloseNotification",
+                ("id",),
+                (),
+                |_, server: &mut Self, (id,): (u32,)| server.send(ServerEvent::Close(id)),
+            );
+            builder.method(
+                "GetCapabilities",
+                (),
+                ("capabilities",),
+                |_, _: &mut Self, _: ()| Ok((Self::capabilities(),)),
+            );
+            builder.method(
+                "GetServerInformation",
+                (),
+                ("name", "vendor", "version", "spec_version"),
+                |_, _: &mut Self, _: ()| {
+                    Ok((
+                        env!("CARGO_PKG_NAME"),
+                        "antonio-hickey",
+                        env!("CARGO_PKG_VERSION"),
+                        SPEC_VERSION,
+                    ))
+                },
+            );
+            builder.signal::<(u32, u32), _>("NotificationClosed", ("id", "reason"));
+            builder.signal::<(u32, String), _>("ActionInvoked", ("id", "action_key"));
+        })
+    }
+
+    /// The optional spec capabilities this server supports
+    fn capabilities() -> Vec<&'static str> {
+        vec!["actions", "body", "body-hyperlinks", "body-markup"]
+    }
+
+    /// Hand an event off to the main loop
+    fn send(&self, event: ServerEvent) -> Result<(), dbus::MethodErr> {
+        self.sender
+            .send(event)
+            .map_err(|_| dbus::MethodErr::failed("revere is shutting down"))
+    }
+}
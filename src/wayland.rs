@@ -0,0 +1,770 @@
+use crate::{config::OutputSelection, error::RevereError};
+use smithay_client_toolkit::{
+    reexports::{
+        calloop::LoopHandle,
+        client::{
+            protocol::{
+                wl_compositor::WlCompositor,
+                wl_keyboard::{self, WlKeyboard},
+                wl_output::{self, WlOutput},
+                wl_pointer::{self, WlPointer},
+                wl_registry::WlRegistry,
+                wl_seat::{self, WlSeat},
+                wl_shm::WlShm,
+                wl_surface::WlSurface,
+                wl_touch::{self, WlTouch},
+            },
+            Attached, Display, GlobalEvent, GlobalManager, Main,
+        },
+        protocols::{
+            unstable::xdg_output::v1::client::{
+                zxdg_output_manager_v1::ZxdgOutputManagerV1, zxdg_output_v1,
+            },
+            viewporter::client::wp_viewporter::WpViewporter,
+            wlr::unstable::layer_shell::v1::client::zwlr_layer_shell_v1::ZwlrLayerShellV1,
+        },
+    },
+    WaylandSource,
+};
+use std::{cell::RefCell, fs::File, os::fd::FromRawFd, rc::Rc};
+
+/// Linux input event codes for the mouse buttons
+const BTN_LEFT: u32 = 0x110;
+const BTN_RIGHT: u32 = 0x111;
+const BTN_MIDDLE: u32 = 0x112;
+
+/// The mouse buttons notifications can be clicked with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+impl MouseButton {
+    /// The mouse button of a Linux input event code, if it's one of them
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            BTN_LEFT => Some(Self::Left),
+            BTN_RIGHT => Some(Self::Right),
+            BTN_MIDDLE => Some(Self::Middle),
+            _ => None,
+        }
+    }
+}
+
+/// How far a finger moves (px) before it's not a tap anymore, but a swipe or a scroll
+const TAP_SLOP: f64 = 10.0;
+
+/// Linux input event codes for the keys notifications can be navigated with
+const KEY_ESC: u32 = 1;
+const KEY_TAB: u32 = 15;
+const KEY_ENTER: u32 = 28;
+const KEY_KPENTER: u32 = 96;
+const KEY_UP: u32 = 103;
+const KEY_LEFT: u32 = 105;
+const KEY_RIGHT: u32 = 106;
+const KEY_DOWN: u32 = 108;
+
+/// The keys notifications can be navigated with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Key {
+    Escape,
+    Enter,
+    Tab,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+impl Key {
+    /// The key of a Linux input event code, if it's one of them
+    fn from_code(code: u32) -> Option<Self> {
+        match code {
+            KEY_ESC => Some(Self::Escape),
+            KEY_ENTER | KEY_KPENTER => Some(Self::Enter),
+            KEY_TAB => Some(Self::Tab),
+            KEY_UP => Some(Self::Up),
+            KEY_DOWN => Some(Self::Down),
+            KEY_LEFT => Some(Self::Left),
+            KEY_RIGHT => Some(Self::Right),
+            _ => None,
+        }
+    }
+}
+
+/// A long lived connection to the wayland server, binding the globals
+/// once so notification windows only have to create their own surfaces.
+pub struct WaylandContext {
+    display: Display,
+    _globals: GlobalManager,
+    pub compositor: Main<WlCompositor>,
+    pub shm: Main<WlShm>,
+    pub layer_shell: Main<ZwlrLayerShellV1>,
+    /// The pointer of the seat, shared by every notification window
+    pub pointer_state: Rc<RefCell<PointerState>>,
+    /// The keyboard of the seat, shared by every notification window
+    pub keyboard_state: Rc<RefCell<KeyboardState>>,
+    /// The touchscreen of the seat, shared by every notification window
+    pub touch_state: Rc<RefCell<TouchState>>,
+    /// The outputs (screens) currently connected
+    pub outputs: Outputs,
+    /// Scales surfaces for fractional scaling (if the compositor supports it)
+    pub viewporter: Option<Main<WpViewporter>>,
+}
+impl WaylandContext {
+    /// Connect to the wayland server, bind the globals and hand the
+    /// wayland events off to the event loop to be dispatched.
+    pub fn connect(event_loop: LoopHandle<'static, ()>) -> Result<Self, RevereError> {
+        // Connect to wayland server getting a Display
+        // then derive a EventQueue, and an attached Display
+        let display = Display::connect_to_env()?;
+        let mut event_queue = display.create_event_queue();
+        let attached_display = (*display).clone().attach(event_queue.token());
+
+        // Keep track of the globals that can come and go (seats and outputs)
+        let pointer_state = Rc::new(RefCell::new(PointerState::default()));
+        let keyboard_state = Rc::new(RefCell::new(KeyboardState::default()));
+        let touch_state = Rc::new(RefCell::new(TouchState::default()));
+        let outputs = Outputs::default();
+        let globals = {
+            let pointer_state = Rc::clone(&pointer_state);
+            let keyboard_state = Rc::clone(&keyboard_state);
+            let touch_state = Rc::clone(&touch_state);
+            let outputs = outputs.clone();
+            let mut xdg_output_manager: Option<Main<ZxdgOutputManagerV1>> = None;
+            let mut seat: Option<(u32, Main<WlSeat>)> = None;
+            GlobalManager::new_with_cb(&attached_display, move |event, registry, _| match event {
+                GlobalEvent::New {
+                    id,
+                    interface,
+                    version,
+                } => match interface.as_str() {
+                    "wl_seat" if seat.is_none() => {
+                        let new_seat = registry.bind::<WlSeat>(version.min(5), id);
+                        Self::assign_seat(&new_seat, &pointer_state, &keyboard_state, &touch_state);
+                        seat = Some((id, new_seat));
+                    }
+                    "wl_output" => {
+                        let output = Output::bind(&registry, id, version, &outputs);
+                        if let Some(manager) = &xdg_output_manager {
+                            output.describe(manager, &outputs);
+                        }
+                        outputs.0.borrow_mut().push(output);
+                    }
+                    "zxdg_output_manager_v1" => {
+                        let manager = registry.bind::<ZxdgOutputManagerV1>(version.min(3), id);
+                        for output in outputs.0.borrow().iter() {
+                            output.describe(&manager, &outputs);
+                        }
+                        xdg_output_manager = Some(manager);
+                    }
+                    _ => {}
+                },
+                GlobalEvent::Removed { id, interface } => match interface.as_str() {
+                    "wl_seat" if seat.as_ref().is_some_and(|(seat_id, _)| *seat_id == id) => {
+                        pointer_state.borrow_mut().release();
+                        keyboard_state.borrow_mut().release();
+                        touch_state.borrow_mut().release();
+                        if let Some((_, seat)) = seat.take() {
+                            if seat.as_ref().version() >= 5 {
+                                seat.release();
+                            }
+                        }
+                    }
+                    "wl_output" => {
+                        let mut outputs = outputs.0.borrow_mut();
+                        if let Some(position) = outputs.iter().position(|output| output.id == id) {
+                            outputs.remove(position).release();
+                        }
+                    }
+                    _ => {}
+                },
+            })
+        };
+
+        // Instantiate the wayland globals every window needs, the
+        // second roundtrip is for the outputs to describe themselves
+        event_queue.sync_roundtrip(&mut (), |_, _, _| {})?;
+        event_queue.sync_roundtrip(&mut (), |_, _, _| {})?;
+        let compositor = globals.instantiate_range::<WlCompositor>(1, 4)?;
+        let shm = globals.instantiate_exact::<WlShm>(1)?;
+        let layer_shell = globals.instantiate_range::<ZwlrLayerShellV1>(1, 4)?;
+        let viewporter = globals.instantiate_exact::<WpViewporter>(1).ok();
+
+        // Dispatch wayland events whenever the server sends any,
+        // ignoring events for objects nothing is listening to
+        event_loop
+            .insert_source(WaylandSource::new(event_queue), |(), queue, _| {
+                queue.dispatch_pending(&mut (), |_, _, _| {})
+            })
+            .map_err(|e| e.error)?;
+
+        Ok(WaylandContext {
+            display,
+            _globals: globals,
+            compositor,
+            shm,
+            layer_shell,
+            pointer_state,
+            keyboard_state,
+            touch_state,
+            outputs,
+            viewporter,
+        })
+    }
+
+    /// Send any buffered requests to the wayland server
+    pub fn flush(&self) -> Result<(), RevereError> {
+        self.display
+            .flush()
+            .map_err(|_| RevereError::DisplayFlushError)
+    }
+
+    /// The outputs to display a notification on, where `None` lets
+    /// the compositor decide (which usually is the focused output)
+    pub fn select_outputs(&self, selection: &OutputSelection) -> Vec<Option<WlOutput>> {
+        let outputs = self.outputs.0.borrow();
+        let selected: Vec<Option<WlOutput>> = match selection {
+            OutputSelection::Focused => Vec::new(),
+            OutputSelection::Primary => outputs
+                .first()
+                .map(|output| Some(output.wl_output.clone()))
+                .into_iter()
+                .collect(),
+            OutputSelection::All => outputs
+                .iter()
+                .map(|output| Some(output.wl_output.clone()))
+                .collect(),
+            OutputSelection::Named(name) => outputs
+                .iter()
+                .filter(|output| output.matches(name))
+                .map(|output| Some(output.wl_output.clone()))
+                .collect(),
+        };
+
+        // Fall back to the compositor's choice when nothing matched
+        if selected.is_empty() {
+            vec![None]
+        } else {
+            selected
+        }
+    }
+
+    /// Listen for pointer, keyboard and touch input once the seat tells us it has them
+    fn assign_seat(
+        seat: &Main<WlSeat>,
+        pointer_state: &Rc<RefCell<PointerState>>,
+        keyboard_state: &Rc<RefCell<KeyboardState>>,
+        touch_state: &Rc<RefCell<TouchState>>,
+    ) {
+        let pointer_handle = Rc::clone(pointer_state);
+        let keyboard_handle = Rc::clone(keyboard_state);
+        let touch_handle = Rc::clone(touch_state);
+        seat.quick_assign(move |seat, event, _| {
+            if let wl_seat::Event::Capabilities { capabilities } = event {
+                let mut state = pointer_handle.borrow_mut();
+                let has_pointer = capabilities.contains(wl_seat::Capability::Pointer);
+                if has_pointer && state.pointer.is_none() {
+                    let pointer = seat.get_pointer();
+                    let event_handle = Rc::clone(&pointer_handle);
+                    pointer.quick_assign(move |_, event, _| {
+                        event_handle.borrow_mut().handle_event(event)
+                    });
+                    state.pointer = Some(pointer.detach());
+                } else if !has_pointer {
+                    state.release();
+                }
+
+                let mut state = keyboard_handle.borrow_mut();
+                let has_keyboard = capabilities.contains(wl_seat::Capability::Keyboard);
+                if has_keyboard && state.keyboard.is_none() {
+                    let keyboard = seat.get_keyboard();
+                    let event_handle = Rc::clone(&keyboard_handle);
+                    keyboard.quick_assign(move |_, event, _| {
+                        event_handle.borrow_mut().handle_event(event)
+                    });
+                    state.keyboard = Some(keyboard.detach());
+                } else if !has_keyboard {
+                    state.release();
+                }
+
+                let mut state = touch_handle.borrow_mut();
+                let has_touch = capabilities.contains(wl_seat::Capability::Touch);
+                if has_touch && state.touch.is_none() {
+                    let touch = seat.get_touch();
+                    let event_handle = Rc::clone(&touch_handle);
+                    touch.quick_assign(move |_, event, _| {
+                        event_handle.borrow_mut().handle_event(event)
+                    });
+                    state.touch = Some(touch.detach());
+                } else if !has_touch {
+                    state.release();
+                }
+            }
+        });
+    }
+}
+
+/// The outputs (screens) currently connected, shared with the windows on them
+#[derive(Clone, Default)]
+pub struct Outputs(Rc<RefCell<Vec<Output>>>);
+impl Outputs {
+    /// The scale of an output, `None` if it's not connected (anymore)
+    pub fn scale_of(&self, wl_output: &WlOutput) -> Option<f64> {
+        self.0
+            .borrow()
+            .iter()
+            .find(|output| &output.wl_output == wl_output)
+            .map(Output::scale)
+    }
+
+    /// The biggest scale of all the outputs
+    pub fn max_scale(&self) -> f64 {
+        self.0
+            .borrow()
+            .iter()
+            .map(Output::scale)
+            .fold(1.0, f64::max)
+    }
+}
+
+/// A connected output (screen)
+struct Output {
+    /// The global name of the output
+    id: u32,
+    wl_output: WlOutput,
+    /// The output's name, like "DP-1"
+    name: Option<String>,
+    /// The output's description, like "Dell Inc. DELL U2720Q"
+    description: Option<String>,
+    /// The integer scale of the output
+    scale: i32,
+    /// The size of the output's current mode (px)
+    mode_size: Option<(i32, i32)>,
+    /// The size of the output in the compositor's space (px)
+    logical_size: Option<(i32, i32)>,
+}
+impl Output {
+    /// Bind an output global, keeping its name, description and scale up to date
+    fn bind(registry: &Attached<WlRegistry>, id: u32, version: u32, outputs: &Outputs) -> Self {
+        let wl_output = registry.bind::<WlOutput>(version.min(4), id);
+        let outputs_handle = outputs.clone();
+        wl_output.quick_assign(move |_, event, _| match event {
+            wl_output::Event::Name { name } => {
+                Self::update(&outputs_handle, id, |output| output.name = Some(name))
+            }
+            wl_output::Event::Description { description } => {
+                Self::update(&outputs_handle, id, |output| {
+                    output.description = Some(description)
+                })
+            }
+            wl_output::Event::Scale { factor } => {
+                Self::update(&outputs_handle, id, |output| output.scale = factor)
+            }
+            wl_output::Event::Mode {
+                flags,
+                width,
+                height,
+                ..
+            } if flags.contains(wl_output::Mode::Current) => {
+                Self::update(&outputs_handle, id, |output| {
+                    output.mode_size = Some((width, height))
+                })
+            }
+            _ => {}
+        });
+
+        Output {
+            id,
+            wl_output: wl_output.detach(),
+            name: None,
+            description: None,
+            scale: 1,
+            mode_size: None,
+            logical_size: None,
+        }
+    }
+
+    /// Ask the xdg output manager for the output's name and description
+    /// (for compositors with outputs older than version 4), and its logical size
+    fn describe(&self, manager: &Main<ZxdgOutputManagerV1>, outputs: &Outputs) {
+        let id = self.id;
+        let outputs_handle = outputs.clone();
+        manager
+            .get_xdg_output(&self.wl_output)
+            .quick_assign(move |_, event, _| match event {
+                zxdg_output_v1::Event::Name { name } => {
+                    Self::update(&outputs_handle, id, |output| {
+                        output.name.get_or_insert(name);
+                    })
+                }
+                zxdg_output_v1::Event::Description { description } => {
+                    Self::update(&outputs_handle, id, |output| {
+                        output.description.get_or_insert(description);
+                    })
+                }
+                zxdg_output_v1::Event::LogicalSize { width, height } => {
+                    Self::update(&outputs_handle, id, |output| {
+                        output.logical_size = Some((width, height))
+                    })
+                }
+                _ => {}
+            });
+    }
+
+    /// Update the output with the global name
+    fn update(outputs: &Outputs, id: u32, update: impl FnOnce(&mut Output)) {
+        if let Some(output) = outputs
+            .0
+            .borrow_mut()
+            .iter_mut()
+            .find(|output| output.id == id)
+        {
+            update(output);
+        }
+    }
+
+    /// How many buffer pixels there are to a surface pixel on the output.
+    ///
+    /// Compositors scaling an output by a fraction let it be known through
+    /// its logical size being smaller than the size of its current mode,
+    /// otherwise it's the integer scale of the output.
+    fn scale(&self) -> f64 {
+        match (self.mode_size, self.logical_size) {
+            (Some((mode_width, mode_height)), Some((logical_width, logical_height)))
+                if logical_width > 0 && logical_height > 0 =>
+            {
+                // The larger sides line up whichever way the output is rotated
+                mode_width.max(mode_height) as f64 / logical_width.max(logical_height) as f64
+            }
+            _ => self.scale.max(1) as f64,
+        }
+    }
+
+    /// Whether the output goes by the name, or has it in its description
+    fn matches(&self, name: &str) -> bool {
+        self.name.as_deref() == Some(name)
+            || self
+                .description
+                .as_deref()
+                .is_some_and(|description| description.contains(name))
+    }
+
+    /// Let go of the output, it's been disconnected
+    fn release(self) {
+        if self.wl_output.as_ref().version() >= 3 {
+            self.wl_output.release();
+        }
+    }
+}
+
+/// The pointer state of the seat, shared by every notification window
+#[derive(Default)]
+pub struct PointerState {
+    pointer: Option<WlPointer>,
+    /// The surface the pointer is over
+    focus: Option<WlSurface>,
+    /// Where the pointer is over the focused surface
+    position: (f64, f64),
+    /// Where a button was last pressed, which one and on which surface, until it's handled
+    pressed: Option<(WlSurface, (f64, f64), MouseButton)>,
+}
+impl PointerState {
+    /// Take the button press on a surface (surface local coordinates)
+    pub fn take_press(&mut self, surface: &WlSurface) -> Option<((f64, f64), MouseButton)> {
+        match &self.pressed {
+            Some((pressed_surface, ..)) if pressed_surface == surface => self
+                .pressed
+                .take()
+                .map(|(_, position, button)| (position, button)),
+            _ => None,
+        }
+    }
+
+    /// Whether the pointer is over a surface
+    pub fn is_over(&self, surface: &WlSurface) -> bool {
+        self.focus.as_ref() == Some(surface)
+    }
+
+    /// Track the pointer focus, position and button presses
+    fn handle_event(&mut self, event: wl_pointer::Event) {
+        match event {
+            wl_pointer::Event::Enter {
+                surface,
+                surface_x,
+                surface_y,
+                ..
+            } => {
+                self.focus = Some(surface);
+                self.position = (surface_x, surface_y);
+            }
+            wl_pointer::Event::Leave { .. } => self.focus = None,
+            wl_pointer::Event::Motion {
+                surface_x,
+                surface_y,
+                ..
+            } => self.position = (surface_x, surface_y),
+            wl_pointer::Event::Button {
+                button,
+                state: wl_pointer::ButtonState::Pressed,
+                ..
+            } => {
+                if let (Some(focus), Some(button)) = (&self.focus, MouseButton::from_code(button)) {
+                    self.pressed = Some((focus.clone(), self.position, button));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Let go of the pointer, the seat lost it
+    fn release(&mut self) {
+        if let Some(pointer) = self.pointer.take() {
+            if pointer.as_ref().version() >= 3 {
+                pointer.release();
+            }
+        }
+        self.focus = None;
+        self.pressed = None;
+    }
+}
+
+/// The keyboard state of the seat, shared by every notification window.
+/// Notification windows only get keyboard focus in keyboard-interactive mode.
+#[derive(Default)]
+pub struct KeyboardState {
+    keyboard: Option<WlKeyboard>,
+    /// The surface with keyboard focus
+    focus: Option<WlSurface>,
+    /// The keys pressed and on which surface, until they're handled
+    pressed: Vec<(WlSurface, Key)>,
+}
+impl KeyboardState {
+    /// Take the key presses on a surface, in the order they were pressed
+    pub fn take_presses(&mut self, surface: &WlSurface) -> Vec<Key> {
+        let (taken, rest) = self
+            .pressed
+            .drain(..)
+            .partition(|(pressed_surface, _)| pressed_surface == surface);
+        self.pressed = rest;
+        taken.into_iter().map(|(_, key)| key).collect()
+    }
+
+    /// Drop the key presses on a surface that won't take them
+    pub fn forget(&mut self, surface: &WlSurface) {
+        self.pressed
+            .retain(|(pressed_surface, _)| pressed_surface != surface);
+    }
+
+    /// Whether a surface has keyboard focus
+    pub fn is_focused(&self, surface: &WlSurface) -> bool {
+        self.focus.as_ref() == Some(surface)
+    }
+
+    /// Track the keyboard focus and key presses
+    fn handle_event(&mut self, event: wl_keyboard::Event) {
+        match event {
+            // Keys are told apart by their codes, the keymap isn't needed
+            wl_keyboard::Event::Keymap { fd, .. } => drop(unsafe { File::from_raw_fd(fd) }),
+            wl_keyboard::Event::Enter { surface, .. } => self.focus = Some(surface),
+            wl_keyboard::Event::Leave { .. } => self.focus = None,
+            wl_keyboard::Event::Key {
+                key,
+                state: wl_keyboard::KeyState::Pressed,
+                ..
+            } => {
+                if let (Some(focus), Some(key)) = (&self.focus, Key::from_code(key)) {
+                    self.pressed.push((focus.clone(), key));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Let go of the keyboard, the seat lost it
+    fn release(&mut self) {
+        if let Some(keyboard) = self.keyboard.take() {
+            if keyboard.as_ref().version() >= 3 {
+                keyboard.release();
+            }
+        }
+        self.focus = None;
+        self.pressed.clear();
+    }
+}
+
+/// A finger swiping a surface sideways
+pub struct Swipe {
+    /// Which touch point it is, a new swipe is a new touch point
+    pub id: i32,
+    /// Where the finger went down and where it is (surface local x)
+    pub start_x: f64,
+    pub x: f64,
+    /// Whether the finger let go, ending the swipe
+    pub released: bool,
+}
+
+/// What a finger on the screen turned out to be doing
+#[derive(PartialEq)]
+enum Gesture {
+    /// It didn't move far enough to tell yet, a tap if it lets go now
+    Undecided,
+    /// It's moving sideways
+    Swipe,
+    /// It's moving some other way, which notifications don't care about
+    Ignored,
+}
+
+/// A finger on the screen
+struct TouchPoint {
+    id: i32,
+    /// The surface it went down on, it stays with it until it lets go
+    surface: WlSurface,
+    /// Where it went down and where it is (surface local coordinates)
+    start: (f64, f64),
+    position: (f64, f64),
+    gesture: Gesture,
+    /// Whether a swipe moved or let go since it was last taken
+    changed: bool,
+    released: bool,
+}
+
+/// The touchscreen state of the seat, shared by every notification window
+#[derive(Default)]
+pub struct TouchState {
+    touch: Option<WlTouch>,
+    /// The fingers on the screen (and swipes that let go, until they're taken)
+    points: Vec<TouchPoint>,
+    /// Where a finger last tapped and on which surface, until it's handled
+    tapped: Option<(WlSurface, (f64, f64))>,
+}
+impl TouchState {
+    /// Take the tap on a surface (surface local coordinates)
+    pub fn take_tap(&mut self, surface: &WlSurface) -> Option<(f64, f64)> {
+        match &self.tapped {
+            Some((tapped_surface, _)) if tapped_surface == surface => {
+                self.tapped.take().map(|(_, position)| position)
+            }
+            _ => None,
+        }
+    }
+
+    /// Take what changed about the swipe on a surface, if anything did
+    pub fn take_swipe(&mut self, surface: &WlSurface) -> Option<Swipe> {
+        let index = self.points.iter().position(|point| {
+            point.gesture == Gesture::Swipe && point.changed && &point.surface == surface
+        })?;
+        let point = &mut self.points[index];
+        point.changed = false;
+        let swipe = Swipe {
+            id: point.id,
+            start_x: point.start.0,
+            x: point.position.0,
+            released: point.released,
+        };
+        if point.released {
+            self.points.remove(index);
+        }
+
+        Some(swipe)
+    }
+
+    /// Whether a finger is on a surface
+    pub fn is_touching(&self, surface: &WlSurface) -> bool {
+        self.points
+            .iter()
+            .any(|point| &point.surface == surface && !point.released)
+    }
+
+    /// Drop the fingers, swipes and tap on a surface that won't take them
+    pub fn forget(&mut self, surface: &WlSurface) {
+        self.points.retain(|point| &point.surface != surface);
+        if matches!(&self.tapped, Some((tapped_surface, _)) if tapped_surface == surface) {
+            self.tapped = None;
+        }
+    }
+
+    /// Track the fingers on the screen, telling taps and swipes apart
+    fn handle_event(&mut self, event: wl_touch::Event) {
+        match event {
+            wl_touch::Event::Down {
+                surface, id, x, y, ..
+            } => self.points.push(TouchPoint {
+                id,
+                surface,
+                start: (x, y),
+                position: (x, y),
+                gesture: Gesture::Undecided,
+                changed: false,
+                released: false,
+            }),
+            wl_touch::Event::Motion { id, x, y, .. } => {
+                let Some(point) = self.point(id) else {
+                    return;
+                };
+                point.position = (x, y);
+                let (dx, dy) = ((x - point.start.0).abs(), (y - point.start.1).abs());
+                if point.gesture == Gesture::Undecided && dx.max(dy) > TAP_SLOP {
+                    point.gesture = match dx > dy {
+                        true => Gesture::Swipe,
+                        false => Gesture::Ignored,
+                    };
+                }
+                point.changed = true;
+            }
+            wl_touch::Event::Up { id, .. } => {
+                let Some(point) = self.point(id) else {
+                    return;
+                };
+                point.released = true;
+                point.changed = true;
+                self.let_go();
+            }
+            // The compositor took the fingers for itself, nothing is a tap anymore
+            wl_touch::Event::Cancel => {
+                for point in &mut self.points {
+                    if point.gesture == Gesture::Undecided {
+                        point.gesture = Gesture::Ignored;
+                    }
+                    point.released = true;
+                    point.changed = true;
+                }
+                self.let_go();
+            }
+            _ => {}
+        }
+    }
+
+    /// A finger on the screen by id, unless it already let go
+    fn point(&mut self, id: i32) -> Option<&mut TouchPoint> {
+        self.points
+            .iter_mut()
+            .find(|point| point.id == id && !point.released)
+    }
+
+    /// Forget the fingers that let go, keeping swipes until they're taken
+    /// and turning the ones that never moved far into taps
+    fn let_go(&mut self) {
+        let tapped = &mut self.tapped;
+        self.points.retain(|point| {
+            if !point.released {
+                return true;
+            }
+            if point.gesture == Gesture::Undecided {
+                *tapped = Some((point.surface.clone(), point.start));
+            }
+            point.gesture == Gesture::Swipe
+        });
+    }
+
+    /// Let go of the touchscreen, the seat lost it
+    fn release(&mut self) {
+        if let Some(touch) = self.touch.take() {
+            if touch.as_ref().version() >= 3 {
+                touch.release();
+            }
+        }
+        self.points.clear();
+        self.tapped = None;
+    }
+}
@@ -1,263 +1,1242 @@
-use crate::{config::WindowConfig, error::RevereError};
+use crate::{
+    config::{Easing, Rgb, TextStyle, WindowConfig, WindowShadow},
+    control::Command,
+    error::RevereError,
+    layout::{self, CardLayout},
+    notification::Notification,
+    wayland::{Key, KeyboardState, MouseButton, Outputs, PointerState, TouchState, WaylandContext},
+};
 use cairo::{Context, Format, ImageSurface};
-use pango::{FontDescription, Layout};
 use pangocairo::functions as pango_cairo;
 use smithay_client_toolkit::{
     reexports::{
-        client::{
-            protocol::{
-                wl_buffer::WlBuffer,
-                wl_compositor::{self, WlCompositor},
-                wl_shm::{Format as WlFormat, WlShm},
-                wl_surface::WlSurface,
-            },
-            Display, EventQueue, GlobalManager,
+        client::protocol::{
+            wl_buffer::WlBuffer,
+            wl_compositor::WlCompositor,
+            wl_output::WlOutput,
+            wl_shm::Format as WlFormat,
+            wl_surface::{self, WlSurface},
         },
-        protocols::wlr::unstable::layer_shell::v1::client::{
-            zwlr_layer_shell_v1::{self, ZwlrLayerShellV1},
-            zwlr_layer_surface_v1::{self, ZwlrLayerSurfaceV1},
+        protocols::{
+            viewporter::client::wp_viewport::WpViewport,
+            wlr::unstable::layer_shell::v1::client::{
+                zwlr_layer_shell_v1,
+                zwlr_layer_surface_v1::{self, KeyboardInteractivity, ZwlrLayerSurfaceV1},
+            },
         },
     },
     shm::DoubleMemPool,
 };
-use std::fs::File;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+/// How narrow an action button gets before the actions that
+/// don't fit are left for the context menu (px)
+const MIN_BUTTON_WIDTH: f64 = 80.0;
+/// How far across the window (as a fraction of its width)
+/// a swipe has to go to dismiss the notification
+const SWIPE_DISMISS_DISTANCE: f64 = 0.3;
+
+/// Something the user clicked on in a notification window
+#[derive(Debug, PartialEq)]
+pub enum Click {
+    /// One of the notification's actions, by action key, from its
+    /// button or the context menu (with the left button)
+    Action(String),
+    /// A hyperlink in the body, by where it points to (with the left button)
+    Link(String),
+    /// Anywhere else on the notification, with any button
+    Body(MouseButton),
+    /// Anywhere else on the notification, with a finger
+    Tap,
+}
+
+/// An action button drawn on the notification window
+struct ActionButton {
+    /// The action key, `None` for the button opening the context menu
+    key: Option<String>,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+impl ActionButton {
+    /// Whether a point (surface local coordinates) lands on the button
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+}
+
+/// A value animated from one number to another
+struct Tween {
+    from: f64,
+    to: f64,
+    /// When the animation started, the value waits at `from` until it does
+    started: Option<Instant>,
+    duration: Duration,
+    easing: Easing,
+}
+impl Tween {
+    /// The value at a point in time
+    fn value(&self, now: Instant) -> f64 {
+        let Some(started) = self.started else {
+            return self.from;
+        };
+        if self.duration.is_zero() {
+            return self.to;
+        }
+        let t = now.saturating_duration_since(started).as_secs_f64() / self.duration.as_secs_f64();
+        self.from + (self.to - self.from) * self.easing.apply(t)
+    }
+
+    /// Whether the value got where it's going
+    fn is_done(&self, now: Instant) -> bool {
+        self.from == self.to
+            || self.duration.is_zero()
+            || self
+                .started
+                .is_some_and(|started| now.saturating_duration_since(started) >= self.duration)
+    }
+
+    /// Start the animation, unless it already started
+    fn start(&mut self, now: Instant) {
+        self.started.get_or_insert(now);
+    }
+
+    /// Animate from wherever the value is at to another value
+    fn retarget(&mut self, to: f64, now: Instant) {
+        self.from = self.value(now);
+        self.to = to;
+        self.started = Some(now);
+    }
+
+    /// Jump straight to a value
+    fn settle(&mut self, value: f64) {
+        self.from = value;
+        self.to = value;
+        self.started = None;
+    }
+}
 
 pub struct NotificationWindow {
-    _layer_shell: Option<ZwlrLayerShellV1>,
     layer_surface: Option<ZwlrLayerSurfaceV1>,
     surface: Option<WlSurface>,
     buffer: Option<WlBuffer>,
-    _compositor: Option<WlCompositor>,
-    _shm: Option<WlShm>,
+    pointer_state: Rc<RefCell<PointerState>>,
+    keyboard_state: Rc<RefCell<KeyboardState>>,
+    touch_state: Rc<RefCell<TouchState>>,
+    /// Whether the window takes keyboard focus when the compositor gives it
+    keyboard_interactive: bool,
+    action_buttons: Vec<ActionButton>,
+    /// The action button picked with the keyboard, by index
+    selected: Option<usize>,
+    /// Where everything was last drawn on the notification card
+    card: Option<CardLayout>,
+    /// Whether the context menu listing all of the actions is
+    /// open, it's drawn in place of the card
+    menu_open: bool,
     pools: DoubleMemPool,
-    display: Display,
-    pub event_queue: EventQueue,
+    closed: Rc<Cell<bool>>,
+    /// Whether the compositor configured the surface, it can't be drawn before
+    configured: Rc<Cell<bool>>,
+    /// Whether the window contents need to be drawn again
+    redraw: Rc<Cell<bool>>,
+    /// Whether the last frame is still waiting on the compositor to display it
+    frame_pending: Rc<Cell<bool>>,
+    width: u32,
+    height: u32,
+    /// How far the window is pushed away from its anchored edge (px)
+    offset: i32,
+    /// The output the window was asked to be displayed on
+    output: Option<WlOutput>,
+    /// The outputs the surface is displayed on, according to the compositor
+    entered: Rc<RefCell<Vec<WlOutput>>>,
+    outputs: Outputs,
+    /// Scales the buffer down to the surface size for fractional scales
+    viewport: Option<WpViewport>,
+    /// The scale the window was last rendered at
+    scale: f64,
+    /// The window contents as they were last painted, presented
+    /// again (faded and moved) for each frame of an animation
+    content: Option<ImageSurface>,
+    /// How much of the window is shown, from `0.0` (slid
+    /// out and faded out) to `1.0` (where it belongs)
+    visibility: Tween,
+    /// How far the window is from its anchored edge on screen,
+    /// catching up with `offset` when the stack changes
+    position: Tween,
+    /// How far the window is swiped sideways from where it belongs (px),
+    /// following the finger until it snaps back or carries on off screen
+    swipe: Tween,
+    /// The touch point swiping the window, with where it was (surface
+    /// local x) and how far the window was swiped on screen back then
+    swipe_grab: Option<(i32, f64, f64)>,
+    /// How far the window was swiped when it was last presented (px)
+    swipe_shown: f64,
+    /// Whether the window moved since it was last presented
+    moved: bool,
+    /// How far the shadow reaches past each window edge (top, right, bottom, left),
+    /// the surface is that much bigger than the window
+    shadow_extents: (i32, i32, i32, i32),
+    /// Creates the input region of the surface
+    compositor: WlCompositor,
 }
 impl NotificationWindow {
-    /// Create a new instance of `NotificationWindow`
-    pub fn try_new(config: &WindowConfig) -> Result<Self, RevereError> {
-        // Connect to wayland server getting a Display
-        // then derive a EventQueue, and an attached Display
-        let display = Display::connect_to_env()?;
-        let mut event_queue = display.create_event_queue();
-        let attached_display = (*display).clone().attach(event_queue.token());
-
-        // Instantiate wayland globals
-        let globals = GlobalManager::new(&attached_display);
-        event_queue.sync_roundtrip(&mut (), |_, _, _| {})?;
-        let compositor = globals.instantiate_exact::<wl_compositor::WlCompositor>(1)?;
-        let shm = globals.instantiate_exact::<WlShm>(1)?;
-        let layer_shell = globals.instantiate_exact::<zwlr_layer_shell_v1::ZwlrLayerShellV1>(1)?;
-
+    /// Create a new instance of `NotificationWindow` on an output (or wherever
+    /// the compositor decides), `width` x `height` px and pushed `offset` px
+    /// away from its anchored edge to stack it with others
+    pub fn try_new(
+        wayland: &WaylandContext,
+        config: &WindowConfig,
+        output: Option<&WlOutput>,
+        (width, height): (u32, u32),
+        offset: i32,
+    ) -> Result<Self, RevereError> {
         // Derive a surface and layer surface from the server
-        let surface = compositor.create_surface();
-        let layer_surface = layer_shell.get_layer_surface(
+        let surface = wayland.compositor.create_surface();
+        let viewport = wayland
+            .viewporter
+            .as_ref()
+            .map(|viewporter| viewporter.get_viewport(&surface).detach());
+        let layer_surface = wayland.layer_shell.get_layer_surface(
             &surface,
-            None, // put the surface on the default output
+            output,
             zwlr_layer_shell_v1::Layer::Overlay,
             "my_notification".to_owned(),
         );
 
-        // Configure the layer surface a bit and commit the changes
-        layer_surface.set_size(config.size.width, config.size.height);
+        // Configure the layer surface a bit, it's sized (and the changes
+        // committed) once the window is put together
         layer_surface.set_anchor(config.placement.x.as_anchor() | config.placement.y.as_anchor());
-        layer_surface.set_margin(
-            config.margin.top,
-            config.margin.right,
-            config.margin.bottom,
-            config.margin.left,
-        );
-        layer_surface.quick_assign(move |layer_surface, event, _| {
-            if let zwlr_layer_surface_v1::Event::Configure { serial, .. } = event {
+        let (top, right, bottom, left) = Self::margin(config, offset, 0);
+        layer_surface.set_margin(top, right, bottom, left);
+        let closed = Rc::new(Cell::new(false));
+        let closed_handle = Rc::clone(&closed);
+        let configured = Rc::new(Cell::new(false));
+        let configured_handle = Rc::clone(&configured);
+        let redraw = Rc::new(Cell::new(true));
+        let redraw_handle = Rc::clone(&redraw);
+        layer_surface.quick_assign(move |layer_surface, event, _| match event {
+            zwlr_layer_surface_v1::Event::Configure { serial, .. } => {
                 layer_surface.ack_configure(serial);
+                configured_handle.set(true);
+                redraw_handle.set(true);
             }
+            zwlr_layer_surface_v1::Event::Closed => closed_handle.set(true),
+            _ => {}
+        });
+
+        // Keep track of the outputs the surface is on, to render at their scale
+        let entered = Rc::new(RefCell::new(Vec::new()));
+        let entered_handle = Rc::clone(&entered);
+        let redraw_handle = Rc::clone(&redraw);
+        surface.quick_assign(move |_, event, _| {
+            let mut entered = entered_handle.borrow_mut();
+            match event {
+                wl_surface::Event::Enter { output } => entered.push(output),
+                wl_surface::Event::Leave { output } => entered.retain(|o| *o != output),
+                _ => {}
+            }
+            redraw_handle.set(true);
         });
-        surface.commit();
 
         // Use a double buffering mechanism for smooth updates
         let pools = DoubleMemPool::new(
-            shm.clone().into(),
+            wayland.shm.clone().into(),
             |_: smithay_client_toolkit::reexports::client::DispatchData| {},
         )?;
 
         // Return a instance of `NotificationWindow`
         let window = Self {
-            _layer_shell: Some(layer_shell.detach()),
             layer_surface: Some(layer_surface.detach()),
             surface: Some(surface.detach()),
-            _compositor: Some(compositor.detach()),
-            _shm: Some(shm.detach()),
+            pointer_state: Rc::clone(&wayland.pointer_state),
+            keyboard_state: Rc::clone(&wayland.keyboard_state),
+            touch_state: Rc::clone(&wayland.touch_state),
+            keyboard_interactive: false,
+            action_buttons: Vec::new(),
+            selected: None,
+            card: None,
+            menu_open: false,
             buffer: None,
-            display,
-            event_queue,
+            closed,
+            configured,
+            redraw,
+            frame_pending: Rc::new(Cell::new(false)),
+            width,
+            height,
+            offset,
+            output: output.cloned(),
+            entered,
+            outputs: wayland.outputs.clone(),
+            viewport,
+            scale: 1.0,
+            content: None,
+            visibility: Tween {
+                from: 0.0,
+                to: 1.0,
+                started: None,
+                duration: config.animation.appear_duration(),
+                easing: config.animation.easing,
+            },
+            position: Tween {
+                from: offset as f64,
+                to: offset as f64,
+                started: None,
+                duration: config.animation.reflow_duration(),
+                easing: config.animation.easing,
+            },
+            swipe: Tween {
+                from: 0.0,
+                to: 0.0,
+                started: None,
+                duration: config.animation.swipe_duration(),
+                easing: config.animation.easing,
+            },
+            swipe_grab: None,
+            swipe_shown: 0.0,
+            moved: false,
+            shadow_extents: Self::shadow_extents(config),
+            compositor: wayland.compositor.detach(),
             pools,
         };
+        window.apply_size();
 
         Ok(window)
     }
 
-    /// Draws/renders the window using a wayland layer surface.
+    /// The height of the window (px)
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Move the window to `offset` px away from its anchored edge, smoothly
+    /// if it's already on screen (the move shows with the next frame)
+    pub fn set_offset(&mut self, offset: i32) {
+        if offset == self.offset {
+            return;
+        }
+        self.offset = offset;
+
+        match self.content {
+            Some(_) => self.position.retarget(offset as f64, Instant::now()),
+            None => self.position.settle(offset as f64),
+        }
+        self.moved = true;
+    }
+
+    /// Mark the window contents as changed so it's drawn again
+    pub fn request_redraw(&self) {
+        self.redraw.set(true);
+    }
+
+    /// The size (width, height) a window needs to fit a notification's
+    /// content, kept within the configured minimum and maximum sizes.
+    pub fn measure(
+        notification: &Notification,
+        has_thumbnail: bool,
+        config: &WindowConfig,
+    ) -> (u32, u32) {
+        let size = &config.size;
+
+        // Pango needs a Cairo context to lay the text out, any will do
+        let Ok(cr) = ImageSurface::create(Format::ARgb32, 1, 1).and_then(|s| Context::new(&s))
+        else {
+            return (size.width, size.height);
+        };
+
+        // Fit the window to the content unwrapped (within the width limits),
+        // then wrap the content to the window to find out how tall it is
+        let natural = CardLayout::new(&cr, notification, has_thumbnail, config, None);
+        let width =
+            (natural.natural_width.ceil() as u32).clamp(size.width, size.max_width.max(size.width));
+        let card_size = (width as f64, size.height as f64);
+        let card = CardLayout::new(&cr, notification, has_thumbnail, config, Some(card_size));
+        let height =
+            (card.height.ceil() as u32).clamp(size.height, size.max_height.max(size.height));
+
+        (width, height)
+    }
+
+    /// The size (width, height) a `width` px wide window needs to fit
+    /// the context menu of a notification, a row for every action
+    fn measure_menu(notification: &Notification, config: &WindowConfig, width: u32) -> (u32, u32) {
+        let rows = notification.actions.len().max(1) as f64;
+        let height = layout::padding(config) * 2.0
+            + rows * layout::button_height(config)
+            + (rows - 1.0) * config.layout.spacing as f64;
+
+        (width, (height.ceil() as u32).max(config.size.height))
+    }
+
+    /// Open the context menu listing all of the notification's actions
+    pub fn open_menu(&mut self) {
+        self.set_menu_open(true);
+    }
+
+    /// Open or close the context menu, the window is drawn again either way
+    fn set_menu_open(&mut self, open: bool) {
+        if self.menu_open != open {
+            self.menu_open = open;
+            self.selected = None;
+            self.request_redraw();
+        }
+    }
+
+    /// Pick an action button with the keyboard, by index
+    fn select(&mut self, selected: Option<usize>) {
+        if self.selected != selected {
+            self.selected = selected;
+            self.request_redraw();
+        }
+    }
+
+    /// Let the window take keyboard focus when the compositor gives it,
+    /// or not. The layer shell only has on-demand focus since version 4,
+    /// exclusive focus would keep the keyboard from everything else.
+    pub fn set_keyboard_interactive(&mut self, interactive: bool) {
+        if self.keyboard_interactive == interactive {
+            return;
+        }
+        self.keyboard_interactive = interactive;
+
+        let (Some(layer_surface), Some(surface)) = (&self.layer_surface, &self.surface) else {
+            return;
+        };
+        if layer_surface.as_ref().version() >= 4 {
+            layer_surface.set_keyboard_interactivity(match interactive {
+                true => KeyboardInteractivity::OnDemand,
+                false => KeyboardInteractivity::None,
+            });
+            surface.commit();
+        }
+    }
+
+    /// Resize the window, which can't be drawn again until the compositor
+    /// configures it with the new size
+    fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        self.width = width;
+        self.height = height;
+
+        if self.surface.is_some() {
+            self.apply_size();
+            self.configured.set(false);
+        }
+    }
+
+    /// Size the surface to fit the window with its shadow around it, only
+    /// taking pointer input over the window itself, and commit the changes
+    fn apply_size(&self) {
+        let (Some(layer_surface), Some(surface)) = (&self.layer_surface, &self.surface) else {
+            return;
+        };
+        let (top, right, bottom, left) = self.shadow_extents;
+        layer_surface.set_size(
+            self.width + (left + right) as u32,
+            self.height + (top + bottom) as u32,
+        );
+
+        let region = self.compositor.create_region();
+        region.add(left, top, self.width as i32, self.height as i32);
+        surface.set_input_region(Some(&region));
+        region.destroy();
+
+        surface.commit();
+    }
+
+    /// Draws/renders the notification in the window using a wayland layer surface.
     pub fn draw(
         &mut self,
-        msg: &str,
-        thumbnail: &mut Option<File>,
+        notification: &Notification,
+        thumbnail: Option<&ImageSurface>,
         config: &WindowConfig,
     ) -> Result<(), RevereError> {
-        if let Some(pool) = self.pools.pool() {
-            // Resize the pool to the size of the surface
-            let width = config.size.width;
-            let height = config.size.height;
-            let bytes_per_px = 4;
-            let size = (width * height * bytes_per_px) as usize;
-            pool.resize(size).unwrap();
-
-            // Create a intermediate buffer to the size of the surface
-            let temp_buffer: Vec<u8> = vec![0; size];
-
-            // Create a Cairo surface using the intermediate buffer
-            let mut surface = ImageSurface::create_for_data(
-                temp_buffer,
-                Format::ARgb32,
-                width as i32,
-                height as i32,
-                (width * bytes_per_px) as i32,
-            )?;
-
-            // Handle the cairo surface context in a localized scope
-            // to avoid any kind of ownership issues with the surface
-            {
-                let cr = Context::new(&surface).expect("some surface");
-
-                // Perform cario drawing operations
-                cr.set_source_rgb(
-                    config.color.bg.red,
-                    config.color.bg.green,
-                    config.color.bg.blue,
-                );
-                cr.paint().ok(); // Fill the background
-                cr.set_source_rgb(
-                    config.color.fg.red,
-                    config.color.fg.green,
-                    config.color.fg.blue,
-                );
+        // Changed content might need a different size
+        if self.redraw.get() {
+            let (width, height) = match self.menu_open {
+                true => Self::measure_menu(notification, config, self.width),
+                false => Self::measure(notification, thumbnail.is_some(), config),
+            };
+            self.resize(width, height);
+        }
 
-                // Check if there's a thumbnail path provided and draw the PNG image
-                if let Some(thumbnail_file) = thumbnail {
-                    if let Ok(image_surface) = ImageSurface::create_from_png(thumbnail_file) {
-                        // Scale the image down by half
-                        image_surface.set_device_scale(2.0, 2.0);
-                        let scaled_width = (image_surface.width() as f64) * 0.5;
-                        let scaled_height = (image_surface.height() as f64) * 0.5;
-
-                        // Draw the image
-                        if let Err(e) = cr.set_source_surface(&image_surface, 0.0, 0.0) {
-                            eprintln!("{e:?}");
-                        }
-                        cr.paint().expect("Failed to draw PNG image");
-
-                        // Draw the image border
-                        cr.rectangle(0.0, 0.0, scaled_width, scaled_height);
-                        cr.set_source_rgba(0.0, 0.0, 0.0, 1.0);
-                        cr.set_line_width(4.0);
-                        if let Err(e) = cr.stroke() {
-                            eprintln!("{e:?}");
-                        }
-                    }
+        let (width, height) = (self.width, self.height);
+        let (menu_open, selected) = (self.menu_open, self.selected);
+        let drawn = self.render(config, |cr| {
+            if menu_open {
+                let menu = Self::draw_menu(cr, notification, config, width, selected);
+                return (menu, None);
+            }
+
+            let card = CardLayout::new(
+                cr,
+                notification,
+                thumbnail.is_some(),
+                config,
+                Some((width as f64, height as f64)),
+            );
+
+            // Fit the image into the icon area keeping its aspect ratio, the
+            // image is loaded at the output scale so it stays sharp
+            if let (Some(image_surface), Some(icon)) = (thumbnail, card.icon) {
+                let fit = (icon.width / image_surface.width() as f64)
+                    .min(icon.height / image_surface.height() as f64);
+                let x = icon.x + (icon.width - image_surface.width() as f64 * fit) / 2.0;
+                let y = icon.y + (icon.height - image_surface.height() as f64 * fit) / 2.0;
+
+                cr.save().ok();
+                cr.translate(x, y);
+                cr.scale(fit, fit);
+                if let Err(e) = cr.set_source_surface(image_surface, 0.0, 0.0) {
+                    eprintln!("{e:?}");
                 }
+                cr.source().set_filter(cairo::Filter::Good);
+                cr.paint().expect("Failed to draw image");
+                cr.restore().ok();
+            }
+
+            // Render the text where the card layout placed it
+            for text in &card.texts {
+                set_source(cr, &config.color.fg, text.alpha);
+                cr.move_to(text.origin.0, text.origin.1);
+                pango_cairo::show_layout(cr, &text.layout);
+            }
+
+            // Render the progress bar, the filled part over the empty track
+            if let Some((rect, value)) = card.progress {
+                let progress_config = &config.layout.progress;
+                let radius = progress_config
+                    .radius
+                    .min(rect.width / 2.0)
+                    .min(rect.height / 2.0)
+                    .max(0.0);
+                cr.save().ok();
+                rounded_rectangle(cr, rect.x, rect.y, rect.width, rect.height, radius);
+                cr.clip_preserve();
+                set_source(cr, &progress_config.background, 1.0);
+                cr.fill().ok();
+                cr.rectangle(rect.x, rect.y, rect.width * value, rect.height);
+                set_source(cr, &progress_config.color, 1.0);
+                cr.fill().ok();
+                cr.restore().ok();
+            }
 
-                // Render the notification text
-                let layout = Self::create_pango_layout(
+            // Render the action buttons along the bottom of the window
+            let action_buttons =
+                Self::draw_action_buttons(cr, notification, config, (width, height), selected);
+            (action_buttons, Some(card))
+        })?;
+
+        if let Some((action_buttons, card)) = drawn {
+            self.action_buttons = action_buttons;
+            self.card = card;
+        }
+
+        Ok(())
+    }
+
+    /// Draws/renders a "+N more" indicator for notifications that don't fit on screen
+    pub fn draw_overflow(
+        &mut self,
+        hidden: usize,
+        config: &WindowConfig,
+    ) -> Result<(), RevereError> {
+        let (width, height) = (self.width, self.height);
+        self.render(config, |cr| {
+            set_source(cr, &config.color.fg, 1.0);
+            let layout = layout::create_pango_layout(
+                cr,
+                &format!("+{hidden} more"),
+                &TextStyle::default(),
+                config,
+                width,
+            );
+            layout.set_alignment(pango::Alignment::Center);
+            let (_, text_height) = layout.pixel_size();
+            cr.move_to(0.0, (height as f64 - text_height as f64) / 2.0);
+            pango_cairo::show_layout(cr, &layout);
+        })?;
+        // Nothing happens on the indicator, so what's done on it isn't kept around
+        self.forget_input();
+
+        Ok(())
+    }
+
+    /// Render the window contents drawn by `paint` over the window background
+    /// and under the window border (clipped to the rounded corners), with the
+    /// shadow around it all, returning whatever `paint` returned.
+    ///
+    /// Nothing is painted (returning `None`) unless the window needs to be
+    /// drawn again, and nothing is rendered at all unless the compositor is
+    /// ready for a new frame with a free buffer to show it in. Animation frames
+    /// present the contents as they were last painted.
+    fn render<T>(
+        &mut self,
+        config: &WindowConfig,
+        paint: impl FnOnce(&Context) -> T,
+    ) -> Result<Option<T>, RevereError> {
+        // Outputs coming, going or changing scale need a redraw at the new scale
+        let scale = self.current_scale();
+        if scale != self.scale || self.content.is_none() {
+            self.redraw.set(true);
+        }
+        let now = Instant::now();
+        let changed = self.redraw.get() || self.moved || self.is_animating_at(now);
+        if !changed || !self.configured.get() || self.frame_pending.get() {
+            return Ok(None);
+        }
+        // Painting with nowhere to show it would take new contents (and what's
+        // clickable in them) for a frame that never makes it on screen, so it
+        // waits for the compositor to release a buffer
+        if self.pools.pool().is_none() {
+            return Ok(None);
+        }
+
+        let painted = match self.redraw.get() {
+            true => {
+                let (content, painted) = self.paint_content(config, scale, paint)?;
+                self.content = Some(content);
+                self.scale = scale;
+                self.redraw.set(false);
+                Some(painted)
+            }
+            false => None,
+        };
+        self.present(config, now)?;
+
+        Ok(painted)
+    }
+
+    /// Paint the window contents drawn by `paint` at a scale, into a
+    /// Cairo surface the size of the wayland surface in buffer pixels
+    fn paint_content<T>(
+        &self,
+        config: &WindowConfig,
+        scale: f64,
+        paint: impl FnOnce(&Context) -> T,
+    ) -> Result<(ImageSurface, T), RevereError> {
+        let (surface_width, surface_height) = self.surface_size();
+        let surface = ImageSurface::create(
+            Format::ARgb32,
+            (surface_width as f64 * scale).ceil() as i32,
+            (surface_height as f64 * scale).ceil() as i32,
+        )?;
+
+        // Handle the cairo surface context in a localized scope
+        // to avoid any kind of ownership issues with the surface
+        let painted = {
+            let cr = Context::new(&surface).expect("some surface");
+
+            // Draw in surface pixels, Cairo scales it all up to buffer pixels,
+            // leaving room for the shadow around the window
+            cr.scale(scale, scale);
+            let (top, _, _, left) = self.shadow_extents;
+            cr.translate(left as f64, top as f64);
+
+            // Perform cario drawing operations, the buffer starts out
+            // transparent so only the window and its shadow are seen
+            let (width, height) = (self.width as f64, self.height as f64);
+            let radius = config
+                .border
+                .radius
+                .min(width / 2.0)
+                .min(height / 2.0)
+                .max(0.0);
+            if let Some(shadow) = &config.shadow {
+                draw_shadow(&cr, shadow, width, height, radius);
+            }
+            rounded_rectangle(&cr, 0.0, 0.0, width, height, radius);
+            set_source(&cr, &config.color.bg, 1.0);
+            cr.fill_preserve().ok(); // Fill the background
+
+            // Keep the contents inside the rounded corners
+            cr.save().ok();
+            cr.clip();
+            let painted = paint(&cr);
+            cr.restore().ok();
+
+            // Draw the window border inside the window edges
+            let border_width = config.border.width as f64;
+            if border_width > 0.0 {
+                let inset = border_width / 2.0;
+                rounded_rectangle(
                     &cr,
-                    msg,
-                    config.font_size,
-                    (width as i32 - 180) as u32,
+                    inset,
+                    inset,
+                    width - border_width,
+                    height - border_width,
+                    (radius - inset).max(0.0),
                 );
-                cr.move_to(180.0, 40.0);
-                pango_cairo::show_layout(&cr, &layout);
-
-                // Draw the window border
-                cr.rectangle(0.0, 0.0, width as f64, height as f64);
-                cr.set_source_rgba(
-                    config.border.color.red,
-                    config.border.color.green,
-                    config.border.color.blue,
-                    config.border.alpha,
-                );
-                cr.set_line_width(config.border.width as f64);
+                set_source(&cr, &config.border.color, config.border.alpha);
+                cr.set_line_width(border_width);
                 if let Err(e) = cr.stroke() {
                     eprintln!("{e:?}");
                 }
             }
 
-            // Copy the Cairo surface data to the Wayland buffer
-            let mmap = pool.mmap();
-            for (i, byte) in mmap.iter_mut().enumerate() {
-                if let Some(surface_data_byte) = surface.data()?.get(i) {
-                    *byte = *surface_data_byte;
+            painted
+        };
+
+        Ok((surface, painted))
+    }
+
+    /// Present the contents as they were last painted, faded and moved to
+    /// where the window is at this point of its animations, returning whether
+    /// a frame was committed (there's no free buffer to show it in otherwise)
+    fn present(&mut self, config: &WindowConfig, now: Instant) -> Result<bool, RevereError> {
+        let (surface_width, surface_height) = self.surface_size();
+        let slide_distance = self.slide_distance(config);
+        let (Some(content), Some(pool)) = (&mut self.content, self.pools.pool()) else {
+            return Ok(false);
+        };
+
+        // Coming in starts with the first frame on screen
+        self.visibility.start(now);
+        let visibility = self.visibility.value(now).clamp(0.0, 1.0);
+        let alpha = match config.animation.fade {
+            true => visibility,
+            false => 1.0,
+        };
+        // A swiped window goes away the way it was swiped instead
+        let slide = match config.animation.slide && self.swipe.to == 0.0 {
+            true => ((1.0 - visibility) * slide_distance as f64).round() as i32,
+            false => 0,
+        };
+        let swipe = self.swipe.value(now).round() as i32;
+        let slide = match config.placement.is_left() {
+            true => slide - swipe,
+            false => slide + swipe,
+        };
+
+        // Resize the pool to the size of the contents, in buffer pixels
+        let (width, height, stride) = (content.width(), content.height(), content.stride());
+        pool.resize((stride * height) as usize)?;
+
+        // Copy the contents to the Wayland buffer, both are premultiplied
+        // ARGB so fading is multiplying every channel by the alpha
+        let data = content.data()?;
+        let mmap = pool.mmap();
+        for (byte, content_byte) in mmap.iter_mut().zip(data.iter()) {
+            *byte = match alpha < 1.0 {
+                true => (*content_byte as f64 * alpha).round() as u8,
+                false => *content_byte,
+            };
+        }
+
+        // Create a buffer from the memory pool for rendering the window
+        // The pool destroys the buffer it replaces once the compositor releases it
+        self.buffer = Some(pool.buffer(0, width, height, stride, WlFormat::Argb8888));
+
+        // Attach the buffer to the wayland surface, then damage the
+        // surface to signal to wayland server to redraw (update) a surface
+        // region, and finally commit the surface.
+        if let (Some(layer_surface), Some(surface)) = (&self.layer_surface, &self.surface) {
+            // Ask to be told when the compositor is ready for the next frame
+            let frame_pending = Rc::clone(&self.frame_pending);
+            frame_pending.set(true);
+            surface
+                .frame()
+                .quick_assign(move |_, _, _| frame_pending.set(false));
+
+            // Let the compositor know how the buffer maps onto the surface
+            match &self.viewport {
+                Some(viewport) => {
+                    viewport.set_destination(surface_width as i32, surface_height as i32)
                 }
+                None if surface.as_ref().version() >= 3 => {
+                    surface.set_buffer_scale(self.scale as i32)
+                }
+                None => {}
             }
 
-            // Create a buffer from the memory pool for rendering the window
-            self.buffer = Some(pool.buffer(
-                0,
-                width as i32,
-                height as i32,
-                (width * bytes_per_px) as i32,
-                WlFormat::Argb8888,
-            ));
-
-            // Attach the buffer to the wayland surface, then damage the
-            // surface to signal to wayland server to redraw (update) a surface
-            // region, and finally commit the surface.
-            if let Some(surface) = &self.surface {
-                surface.attach(self.buffer.as_ref(), 0, 0);
-                //surface.damage(0, 0, width as i32, height as i32);
-                surface.commit();
+            // Move the window along with the buffer, so they change together
+            let offset = self.position.value(now).round() as i32;
+            let (top, right, bottom, left) = Self::margin(config, offset, slide);
+            layer_surface.set_margin(top, right, bottom, left);
+
+            surface.attach(self.buffer.as_ref(), 0, 0);
+            match surface.as_ref().version() >= 4 {
+                true => surface.damage_buffer(0, 0, width, height),
+                false => surface.damage(0, 0, surface_width as i32, surface_height as i32),
             }
+            surface.commit();
+        }
+        self.moved = false;
+        self.swipe_shown = swipe as f64;
+
+        Ok(true)
+    }
+
+    /// Draw the next frame of the window going away
+    pub fn draw_closing(&mut self, config: &WindowConfig) -> Result<(), RevereError> {
+        let now = Instant::now();
+        if self.configured.get() && !self.frame_pending.get() && self.is_animating_at(now) {
+            self.present(config, now)?;
         }
+        // Nor on a window going away
+        self.forget_input();
 
         Ok(())
     }
 
-    /// Flush the internal display buffer to the server socket.
+    /// Start animating the window away, it's closed once it's done
+    pub fn start_closing(&mut self) {
+        self.visibility.retarget(0.0, Instant::now());
+    }
+
+    /// Whether the window is done going away, and can be closed
+    pub fn is_done_closing(&self) -> bool {
+        let now = Instant::now();
+        self.visibility.to == 0.0
+            && (self.content.is_none() || (self.visibility.is_done(now) && self.swipe.is_done(now)))
+    }
+
+    /// Whether the window is in the middle of an animation
+    /// (or waiting to be moved), needing frames drawn
+    pub fn is_animating(&self) -> bool {
+        self.moved || self.is_animating_at(Instant::now())
+    }
+
+    /// Whether the window is in the middle of an animation at a point in time
+    fn is_animating_at(&self, now: Instant) -> bool {
+        !self.visibility.is_done(now) || !self.position.is_done(now) || !self.swipe.is_done(now)
+    }
+
+    /// The scale to render the window at, the biggest scale of the outputs
+    /// it's on (or the one it's going on before the compositor says so).
     ///
-    /// Non - blocking: If not all the requests could be written
-    /// then returns RevereError::DisplayFlushError
-    pub fn flush_display(&mut self) -> Result<(), RevereError> {
-        // Destroy all the proxies attached to the event queue
-        // before flushing the display, this is to clean up the
-        // unsafe code block in `draw()` ensuring all memory safety.
+    /// Without a viewport the buffer can only be scaled by whole numbers,
+    /// so fractional scales are rounded up and the compositor scales down.
+    fn current_scale(&self) -> f64 {
+        let entered = self.entered.borrow();
+        let scale = match (entered.is_empty(), &self.output) {
+            (false, _) => entered
+                .iter()
+                .filter_map(|output| self.outputs.scale_of(output))
+                .fold(1.0, f64::max),
+            (true, Some(output)) => self.outputs.scale_of(output).unwrap_or(1.0),
+            (true, None) => self.outputs.max_scale(),
+        };
+
+        match self.viewport {
+            Some(_) => scale,
+            None => scale.ceil(),
+        }
+    }
+
+    /// Take the latest click on the window, if there's one not handled yet.
+    /// Clicks opening or closing the context menu are handled here.
+    pub fn take_click(&mut self) -> Option<Click> {
+        let surface = self.surface.as_ref()?;
+        let pressed = self.pointer_state.borrow_mut().take_press(surface);
+        let ((x, y), button) = match pressed {
+            Some((position, button)) => (position, Some(button)),
+            None => (self.touch_state.borrow_mut().take_tap(surface)?, None),
+        };
+        // Taps use buttons and links like left clicks do
+        let primary = button.unwrap_or(MouseButton::Left) == MouseButton::Left;
+
+        // The window is drawn inside the room left for its shadow
+        let (top, _, _, left) = self.shadow_extents;
+        let (x, y) = (x - left as f64, y - top as f64);
+
+        let pressed = self
+            .action_buttons
+            .iter()
+            .find(|action_button| action_button.contains(x, y))
+            .filter(|_| primary)
+            .map(|action_button| action_button.key.clone());
+
+        // Clicking anywhere closes the context menu, an action on the way
+        if self.menu_open {
+            self.set_menu_open(false);
+            return pressed.flatten().map(Click::Action);
+        }
+
+        match pressed {
+            Some(Some(key)) => Some(Click::Action(key)),
+            Some(None) => {
+                self.set_menu_open(true);
+                None
+            }
+            None => match self.card.as_ref().and_then(|card| card.link_at(x, y)) {
+                Some(href) if primary => Some(Click::Link(href.to_owned())),
+                _ => Some(button.map_or(Click::Tap, Click::Body)),
+            },
+        }
+    }
+
+    /// Take the latest keyboard command on the window, if there's one not
+    /// handled yet. Keys picking an action button or opening and closing
+    /// the context menu are handled here.
+    pub fn take_command(&mut self) -> Option<Command> {
+        let surface = self.surface.as_ref()?;
+        let keys = self.keyboard_state.borrow_mut().take_presses(surface);
+
+        for key in keys {
+            let count = self.action_buttons.len();
+            let selected = self.selected.filter(|&index| index < count);
+            match key {
+                Key::Left | Key::Up if count > 0 => self.select(Some(
+                    selected.map_or(count - 1, |i| (i + count - 1) % count),
+                )),
+                Key::Right | Key::Down | Key::Tab if count > 0 => {
+                    self.select(Some(selected.map_or(0, |i| (i + 1) % count)))
+                }
+                // Enter invokes the picked action, or the default action if none is picked
+                Key::Enter => match selected.map(|index| self.action_buttons[index].key.clone()) {
+                    Some(Some(key)) => {
+                        self.set_menu_open(false);
+                        return Some(Command::Invoke(Some(key)));
+                    }
+                    Some(None) => {
+                        self.set_menu_open(true);
+                        self.select(Some(0));
+                    }
+                    None if self.menu_open => {}
+                    None => return Some(Command::Invoke(None)),
+                },
+                Key::Escape if self.menu_open => self.set_menu_open(false),
+                Key::Escape => return Some(Command::Dismiss),
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Whether the window has keyboard focus
+    pub fn has_keyboard_focus(&self) -> bool {
+        self.surface
+            .as_ref()
+            .is_some_and(|surface| self.keyboard_state.borrow().is_focused(surface))
+    }
+
+    /// Follow a finger swiping the window sideways, returning whether it
+    /// swiped far enough to dismiss the notification. The window snaps
+    /// back when it lets go before that, or carries on the way it was
+    /// swiped (going away) if not.
+    pub fn follow_swipe(&mut self, config: &WindowConfig) -> bool {
+        let Some(surface) = &self.surface else {
+            return false;
+        };
+        let now = Instant::now();
+        let Some(swipe) = self.touch_state.borrow_mut().take_swipe(surface) else {
+            // The seat lost the touchscreen in the middle of a swipe
+            if self.swipe_grab.is_some() && !self.touch_state.borrow().is_touching(surface) {
+                self.swipe_grab = None;
+                self.swipe.retarget(0.0, now);
+            }
+            return false;
+        };
+
+        // The finger is tracked on the surface, which moves along with it,
+        // so how far the window moved on screen since adds to how far it went
+        let (last_x, last_shown) = match self.swipe_grab {
+            Some((id, x, shown)) if id == swipe.id => (x, shown),
+            _ => (swipe.start_x, self.swipe_shown),
+        };
+        let swiped = self.swipe.value(now) + (swipe.x - last_x) + (self.swipe_shown - last_shown);
+        self.swipe.settle(swiped);
+        self.swipe_grab = Some((swipe.id, swipe.x, self.swipe_shown));
+        self.moved = true;
+        if !swipe.released {
+            return false;
+        }
+
+        self.swipe_grab = None;
+        let dismissed = swiped.abs() >= self.width as f64 * SWIPE_DISMISS_DISTANCE;
+        match dismissed {
+            true => {
+                let distance = self.slide_distance(config) as f64;
+                self.swipe.retarget(swiped.signum() * distance, now);
+            }
+            false => self.swipe.retarget(0.0, now),
+        }
+
+        dismissed
+    }
+
+    /// Whether a finger is on the window
+    pub fn is_touched(&self) -> bool {
+        self.surface
+            .as_ref()
+            .is_some_and(|surface| self.touch_state.borrow().is_touching(surface))
+    }
+
+    /// Whether the pointer is over the window
+    pub fn is_hovered(&self) -> bool {
+        self.surface
+            .as_ref()
+            .is_some_and(|surface| self.pointer_state.borrow().is_over(surface))
+    }
+
+    /// Drop the keys pressed and touches made on the window,
+    /// for windows that don't take them
+    fn forget_input(&self) {
         if let Some(surface) = &self.surface {
+            self.keyboard_state.borrow_mut().forget(surface);
+            self.touch_state.borrow_mut().forget(surface);
+        }
+    }
+
+    /// Whether the compositor closed the layer surface on us
+    pub fn is_closed(&self) -> bool {
+        self.closed.get()
+    }
+
+    /// Destroy the window's surfaces, taking it off screen
+    /// once the wayland context flushes the requests.
+    pub fn close(&mut self) {
+        self.forget_input();
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+        if let Some(surface) = self.surface.take() {
             surface.destroy();
         }
-        if let Some(layer_surface) = &self.layer_surface {
+        if let Some(layer_surface) = self.layer_surface.take() {
             layer_surface.destroy();
         }
-        if let Some(buffer) = &self.buffer {
-            buffer.destroy();
+        // The pool destroys the buffer once the compositor releases it
+        self.buffer = None;
+    }
+
+    /// The layer surface margins (top, right, bottom, left) for the window,
+    /// where the offset is added to the margin of the anchored vertical edge,
+    /// and the window is slid `slide` px past the anchored horizontal edge.
+    ///
+    /// The room left for the shadow is taken off the margins, so the
+    /// window itself is where the margins say it is.
+    fn margin(config: &WindowConfig, offset: i32, slide: i32) -> (i32, i32, i32, i32) {
+        let margin = &config.margin;
+        let (top, right, bottom, left) = Self::shadow_extents(config);
+        let (mut top, mut right, mut bottom, mut left) = (
+            margin.top - top,
+            margin.right - right,
+            margin.bottom - bottom,
+            margin.left - left,
+        );
+        match config.placement.is_bottom() {
+            true => bottom += offset,
+            false => top += offset,
+        }
+        match config.placement.is_left() {
+            true => left -= slide,
+            false => right -= slide,
+        }
+
+        (top, right, bottom, left)
+    }
+
+    /// How far the shadow reaches past each window edge (top, right, bottom, left)
+    fn shadow_extents(config: &WindowConfig) -> (i32, i32, i32, i32) {
+        config
+            .shadow
+            .as_ref()
+            .map_or((0, 0, 0, 0), WindowShadow::extents)
+    }
+
+    /// How far the window has to slide to be off screen,
+    /// past the anchored horizontal edge (px)
+    fn slide_distance(&self, config: &WindowConfig) -> i32 {
+        let (surface_width, _) = self.surface_size();
+        surface_width as i32
+            + match config.placement.is_left() {
+                true => config.margin.left,
+                false => config.margin.right,
+            }
+    }
+
+    /// The size (width, height) of the surface, the window with its shadow around it
+    fn surface_size(&self) -> (u32, u32) {
+        let (top, right, bottom, left) = self.shadow_extents;
+        (
+            self.width + (left + right) as u32,
+            self.height + (top + bottom) as u32,
+        )
+    }
+
+    /// Helper function to draw a row of buttons, one per notification action,
+    /// along the bottom of the window returning where each button was drawn.
+    /// When there are more actions than fit, the last button opens the
+    /// context menu instead.
+    fn draw_action_buttons(
+        cr: &Context,
+        notification: &Notification,
+        config: &WindowConfig,
+        (window_width, window_height): (u32, u32),
+        selected: Option<usize>,
+    ) -> Vec<ActionButton> {
+        let element = &config.layout.actions;
+        let actions: Vec<_> = notification.button_actions().collect();
+        if !element.visible || actions.is_empty() {
+            return Vec::new();
+        }
+
+        // Keep as many buttons as fit, leaving the last spot for the menu if not all do
+        let padding = layout::padding(config);
+        let fit =
+            ((window_width as f64 - padding) / (MIN_BUTTON_WIDTH + padding)).max(1.0) as usize;
+        let mut labels: Vec<_> = actions
+            .iter()
+            .map(|action| (Some(action.key.clone()), action.label.as_str()))
+            .collect();
+        if labels.len() > fit {
+            labels.truncate(fit - 1);
+            labels.push((None, "…"));
+        }
+
+        // Split the width of the window evenly between the buttons
+        let height = layout::button_height(config);
+        let y = window_height as f64 - padding - height;
+        let width =
+            (window_width as f64 - padding * (labels.len() as f64 + 1.0)) / labels.len() as f64;
+
+        let mut buttons = Vec::new();
+        for (i, (key, label)) in labels.into_iter().enumerate() {
+            let button = ActionButton {
+                key,
+                x: padding + i as f64 * (width + padding),
+                y,
+                width,
+                height,
+            };
+            Self::draw_button(cr, &button, label, selected == Some(i), config);
+            buttons.push(button);
         }
 
-        // Flush the display
-        self.display
-            .flush()
-            .map_err(|_| RevereError::DisplayFlushError)
+        buttons
     }
 
-    /// Helper function to create a Pango layout for better text handeling like
-    /// absolute size, text wrapping, and other stuff I'm not currently leveraging
-    /// but may in the future like diff fonts, text alignment, and ellipsization.
-    fn create_pango_layout(cr: &Context, text: &str, font_size: u8, max_width: u32) -> Layout {
-        // Font stuff
-        let mut font = FontDescription::from_string(&format!("sans {}", font_size));
-        font.set_absolute_size(font_size as f64 * pango::SCALE as f64);
+    /// Helper function to draw the context menu, a column of buttons for
+    /// every one of the notification's actions, returning where each
+    /// button was drawn.
+    fn draw_menu(
+        cr: &Context,
+        notification: &Notification,
+        config: &WindowConfig,
+        window_width: u32,
+        selected: Option<usize>,
+    ) -> Vec<ActionButton> {
+        let padding = layout::padding(config);
+        let height = layout::button_height(config);
+        let spacing = config.layout.spacing as f64;
+
+        let mut buttons = Vec::new();
+        for (i, action) in notification.actions.iter().enumerate() {
+            let button = ActionButton {
+                key: Some(action.key.clone()),
+                x: padding,
+                y: padding + i as f64 * (height + spacing),
+                width: window_width as f64 - padding * 2.0,
+                height,
+            };
+            // The default action might not have a label to show
+            let label = match action.label.is_empty() {
+                true => &action.key,
+                false => &action.label,
+            };
+            Self::draw_button(cr, &button, label, selected == Some(i), config);
+            buttons.push(button);
+        }
+
+        buttons
+    }
 
-        // Layout stuff
-        let layout = pango_cairo::create_layout(cr).expect("Cannot create pango layout");
-        layout.set_font_description(Some(&font));
-        layout.set_width(max_width as i32 * pango::SCALE);
-        layout.set_wrap(pango::WrapMode::Word);
-        layout.set_text(text);
+    /// Helper function to draw an action button's outline and label,
+    /// tinted if it's picked with the keyboard
+    fn draw_button(
+        cr: &Context,
+        button: &ActionButton,
+        label: &str,
+        selected: bool,
+        config: &WindowConfig,
+    ) {
+        let style = &config.layout.actions.style;
 
-        layout
+        // Draw the button outline
+        cr.rectangle(button.x, button.y, button.width, button.height);
+        if selected {
+            set_source(cr, &config.color.fg, 0.2);
+            cr.fill_preserve().ok();
+        }
+        set_source(cr, &config.color.fg, 1.0);
+        cr.set_line_width(1.0);
+        if let Err(e) = cr.stroke() {
+            eprintln!("{e:?}");
+        }
+
+        // Draw the button label centered vertically in the button
+        let layout = layout::create_pango_layout(cr, label, style, config, button.width as u32);
+        if style.align.is_none() {
+            layout.set_alignment(pango::Alignment::Center);
+        }
+        let (_, text_height) = layout.pixel_size();
+        cr.move_to(
+            button.x,
+            button.y + (button.height - text_height as f64) / 2.0,
+        );
+        pango_cairo::show_layout(cr, &layout);
+    }
+}
+
+/// Set the Cairo source to a color, with its alpha multiplied by `alpha`
+fn set_source(cr: &Context, color: &Rgb, alpha: f64) {
+    cr.set_source_rgba(color.red, color.green, color.blue, color.alpha * alpha);
+}
+
+/// Add a rectangle with corners rounded by `radius` to the current path
+fn rounded_rectangle(cr: &Context, x: f64, y: f64, width: f64, height: f64, radius: f64) {
+    use std::f64::consts::{FRAC_PI_2, PI};
+
+    cr.new_sub_path();
+    cr.arc(x + width - radius, y + radius, radius, -FRAC_PI_2, 0.0);
+    cr.arc(
+        x + width - radius,
+        y + height - radius,
+        radius,
+        0.0,
+        FRAC_PI_2,
+    );
+    cr.arc(x + radius, y + height - radius, radius, FRAC_PI_2, PI);
+    cr.arc(x + radius, y + radius, radius, PI, PI + FRAC_PI_2);
+    cr.close_path();
+}
+
+/// Draw a drop shadow under a `width` x `height` window, Cairo can't blur
+/// so the shadow is faded out by stacking translucent layers that get
+/// bigger the further they reach past the window edges. The shadow is
+/// cut out under the window so it doesn't show through a translucent one.
+fn draw_shadow(cr: &Context, shadow: &WindowShadow, width: f64, height: f64, radius: f64) {
+    let layers = shadow.blur.max(1);
+    let (x, y) = (shadow.offset_x as f64, shadow.offset_y as f64);
+    cr.save().ok();
+    if let Ok((x1, y1, x2, y2)) = cr.clip_extents() {
+        cr.rectangle(x1, y1, x2 - x1, y2 - y1);
+        rounded_rectangle(cr, 0.0, 0.0, width, height, radius);
+        cr.set_fill_rule(cairo::FillRule::EvenOdd);
+        cr.clip();
+        cr.set_fill_rule(cairo::FillRule::Winding);
+    }
+    set_source(cr, &shadow.color, 1.0 / layers as f64);
+    for layer in 0..layers {
+        let spread = shadow.blur as f64 * (layer + 1) as f64 / layers as f64;
+        rounded_rectangle(
+            cr,
+            x - spread,
+            y - spread,
+            width + spread * 2.0,
+            height + spread * 2.0,
+            radius + spread,
+        );
+        cr.fill().ok();
     }
+    cr.restore().ok();
 }